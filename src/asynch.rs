@@ -0,0 +1,347 @@
+//! Asynchronous driver, built on the [`embedded-hal-async`](https://crates.io/crates/embedded-hal-async) I²C trait.
+//!
+//! This module mirrors the blocking API, but every operation which talks to the STMPE1600 is an
+//! `async fn`, so that I/O on the expander does not stall other tasks running on the same executor.
+//! Both drivers are generated from the same source, so they share their documentation, and the
+//! examples show the blocking calls, which are awaited on this driver.
+//! The driver is constructed with [`Stmpe1600Builder::build_async`](crate::Stmpe1600Builder::build_async),
+//! and shares all of its configuration options with the blocking driver.
+//!
+//! Access to the I²C bus is serialised through an async mutex, so pins may be used from several
+//! tasks on the same executor.
+//!
+//! # Waiting for interrupts
+//! If the host pin connected to the STMPE1600's interrupt output is given to
+//! [`Stmpe1600Builder::interrupt_pin`](crate::Stmpe1600Builder::interrupt_pin), and implements
+//! [`embedded_hal_async::digital::Wait`], interrupt pins implement `Wait` too. Any number of pins
+//! may wait at the same time, from different tasks: one of them waits on the host pin, and reads
+//! the interrupt status when it is asserted, waking the others to check for their own interrupts.
+//! While a pin is waiting, the host pin cannot be read by anything else, so
+//! [`Stmpe1600::interrupt_asserted`] returns `true`.
+//!
+//! Edges are taken from the pin's interrupts, so only those allowed by its [`Trigger`] are seen,
+//! and [`Stmpe1600::get_interrupts`] and [`Stmpe1600::get_interrupt_events`] should not be used
+//! at the same time, as they consume the interrupts of every pin.
+//!
+//! ```rust,ignore
+//! use embedded_hal_async::digital::Wait;
+//!
+//! let stmpe1600 = Stmpe1600Builder::new(i2c)
+//!     .interrupts(Polarity::Low)
+//!     .interrupt_pin(int_pin)
+//!     .build_async()
+//!     .await?;
+//! let mut button = stmpe1600.pin_interrupt(0).await?;
+//! button.wait_for_falling_edge().await?;
+//! ```
+//!
+//! # Example
+//! ```rust,ignore
+//! use stmpe1600::Stmpe1600Builder;
+//!
+//! let i2c = /* construct something implementing embedded_hal_async::i2c::I2c */;
+//! let mut stmpe1600 = Stmpe1600Builder::new(i2c).build_async().await?;
+//!
+//! let mut output_pin = stmpe1600.pin_output(1).await?;
+//! output_pin.set_high().await?;
+//! ```
+
+use crate::device::{Async, Register, Stmpe1600Device};
+use crate::interrupts::{self, Handlers};
+use crate::notifier::Notifier;
+use crate::pins::modes::{self, Input, Interrupt, Output};
+use crate::pins::{define_parts, impl_pin};
+use crate::{
+	all_inputs, impl_driver, ButtonEvents, ChangeDetector, Clock, Debouncer, Edge, Error,
+	EventProducer, InterruptEvent, InterruptEvents, InterruptHandler, InterruptInput,
+	InterruptOutput, NoInterruptPin, PinIndex, PinMode, PinNum, PinSet, Polarity,
+	TimestampedEvents, Trigger,
+};
+use core::cell::Cell;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embedded_hal::digital::{ErrorType, InputPin, PinState};
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+/// A struct representing the asynchronous STMPE1600 device driver.
+///
+/// `INT` is the host pin connected to the STMPE1600's interrupt output, if one was given to
+/// [`Stmpe1600Builder::interrupt_pin`](crate::Stmpe1600Builder::interrupt_pin).
+#[derive(Debug)]
+pub struct Stmpe1600<I2C, INT = NoInterruptPin> {
+	device: Mutex<NoopRawMutex, Stmpe1600Device<I2C, Async>>,
+	handlers: Handlers,
+	int: Mutex<NoopRawMutex, INT>,
+	notifier: Notifier,
+	split: Cell<bool>,
+}
+
+/// The locked device, as handed out by [`Stmpe1600::device`].
+type DeviceGuard<'a, I2C> = MutexGuard<'a, NoopRawMutex, Stmpe1600Device<I2C, Async>>;
+
+impl<I2C, INT> Stmpe1600<I2C, INT> {
+	pub(crate) fn new(device: Stmpe1600Device<I2C, Async>, int: INT) -> Stmpe1600<I2C, INT> {
+		Stmpe1600 {
+			device: Mutex::new(device),
+			handlers: Handlers::new(),
+			int: Mutex::new(int),
+			notifier: Notifier::new(),
+			split: Cell::new(false),
+		}
+	}
+
+	/// Locks the device, waiting for any other task using it to finish.
+	///
+	/// This never fails, but returns a `Result` like the blocking driver's does.
+	async fn device<E>(&self) -> Result<DeviceGuard<'_, I2C>, Error<E>> {
+		Ok(self.device.lock().await)
+	}
+
+	/// Called once the interrupt status has been read into the latch, to wake the tasks waiting
+	/// on interrupt pins.
+	fn interrupts_read(&self) {
+		self.notifier.notify();
+	}
+}
+
+impl<I2C, INT> Stmpe1600<I2C, INT>
+where
+	INT: InterruptInput,
+{
+	/// Reads the host pin to find out whether the interrupt output, which is driven to `polarity`
+	/// while an interrupt is pending, is asserted.
+	///
+	/// While an interrupt pin is waiting on the host pin through [`Wait`], the host pin cannot be
+	/// read, so it is assumed to be asserted.
+	fn read_interrupt_pin<E>(&self, polarity: Polarity) -> Result<bool, Error<E>> {
+		match self.int.try_lock() {
+			Ok(mut int) => interrupts::is_asserted(&mut *int, polarity),
+			Err(_) => Ok(true),
+		}
+	}
+}
+
+impl_driver!(
+	[async],
+	[.await],
+	"# embassy_futures::block_on(async {
+let mut stmpe1600 = Stmpe1600Builder::new(I2cMock::new(&[]))
+    .build_async()
+    .await
+    .unwrap();
+let pin = stmpe1600.pin_input_const::<16>().await;
+# });"
+);
+
+define_parts!();
+
+impl_pin! {
+	/// A single I/O pin on the STMPE1600, accessed asynchronously.
+	///
+	/// This is the asynchronous counterpart of [`crate::Pin`]; see its documentation for details on
+	/// pin modes. As `embedded-hal-async` has no traits for reading and writing pins, the operations
+	/// are provided as inherent `async fn`s instead.
+	///
+	/// As with the blocking pins, `P` is the pin's index, either a [`u8`] known at runtime or a
+	/// [`PinNum`] fixed at compile time.
+	[async], [.await]
+}
+
+impl<'a, E, I2C, P, INT> Pin<'a, I2C, Input, P, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+{
+	/// Is the input pin high?
+	pub async fn is_high(&mut self) -> Result<bool, Error<E>> {
+		self.read_level().await
+	}
+
+	/// Is the input pin low?
+	pub async fn is_low(&mut self) -> Result<bool, Error<E>> {
+		Ok(!self.read_level().await?)
+	}
+}
+
+impl<'a, E, I2C, P, INT> Pin<'a, I2C, Output, P, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+{
+	/// Drives the pin low.
+	pub async fn set_low(&mut self) -> Result<(), Error<E>> {
+		self.write_level(false).await
+	}
+
+	/// Drives the pin high.
+	pub async fn set_high(&mut self) -> Result<(), Error<E>> {
+		self.write_level(true).await
+	}
+
+	/// Is the pin set to drive high?
+	///
+	/// This reads back the output register (or its cached copy), not the level on the pin itself.
+	pub async fn is_set_high(&mut self) -> Result<bool, Error<E>> {
+		self.output_level().await
+	}
+
+	/// Is the pin set to drive low?
+	///
+	/// This reads back the output register (or its cached copy), not the level on the pin itself.
+	pub async fn is_set_low(&mut self) -> Result<bool, Error<E>> {
+		Ok(!self.output_level().await?)
+	}
+
+	/// Toggles the level the pin is driving.
+	pub async fn toggle(&mut self) -> Result<(), Error<E>> {
+		self.toggle_level().await
+	}
+}
+
+impl<'a, E, I2C, P, INT> Pin<'a, I2C, Interrupt, P, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+{
+	/// Is the input pin high?
+	pub async fn is_high(&mut self) -> Result<bool, Error<E>> {
+		self.read_level().await
+	}
+
+	/// Is the input pin low?
+	pub async fn is_low(&mut self) -> Result<bool, Error<E>> {
+		Ok(!self.read_level().await?)
+	}
+}
+
+impl<'a, E, I2C, P, INT> Pin<'a, I2C, Interrupt, P, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InputPin + Wait,
+	P: PinIndex,
+{
+	/// Waits until the pin is at the level `high`, returning straight away if it already is.
+	async fn wait_for_level(&mut self, high: bool) -> Result<(), Error<E>> {
+		self.driver
+			.device
+			.lock()
+			.await
+			.clear_pending(self.pin.index());
+		if self.read_level().await? == high {
+			return Ok(());
+		}
+		let edge = if high { Edge::Rising } else { Edge::Falling };
+		self.check_reported(Some(edge)).await?;
+		self.wait_for_event(|event| (event.level == PinState::High) == high)
+			.await
+	}
+
+	/// Waits for an interrupt on `edge` of the pin, or on either edge if `edge` is `None`.
+	///
+	/// An interrupt which is already latched is not cleared first, so that no edge is missed
+	/// between two calls.
+	async fn wait_for_edge(&mut self, edge: Option<Edge>) -> Result<(), Error<E>> {
+		self.check_reported(edge).await?;
+		self.wait_for_event(|event| edge.is_none() || edge == Some(event.edge))
+			.await
+	}
+
+	/// Checks that an interrupt on `edge` of the pin, or on either edge if `edge` is `None`, can
+	/// be reported at all, so that waiting for it does not hang forever.
+	async fn check_reported(&self, edge: Option<Edge>) -> Result<(), Error<E>> {
+		let mut dev = self.driver.device.lock().await;
+		let trigger = dev.trigger(self.pin.index());
+		if edge.is_some_and(|edge| !trigger.reports(edge)) {
+			return Err(Error::EdgeFiltered);
+		}
+		let iegpior = dev.read_reg_cached(Register::IEGPIOR).await?;
+		if iegpior & self.pin.mask() == 0 {
+			return Err(Error::PinMasked);
+		}
+		Ok(())
+	}
+
+	/// Waits for an interrupt of the pin which `accept` returns `true` for.
+	///
+	/// One waiting task at a time holds the host's interrupt pin, and reads the interrupt status
+	/// into the latch once it is asserted. The other tasks wait on the notifier, checking the latch
+	/// for their own pin whenever the status has been read, until they can take over the host pin.
+	async fn wait_for_event<F>(&mut self, accept: F) -> Result<(), Error<E>>
+	where
+		F: Fn(&InterruptEvent) -> bool,
+	{
+		let driver = self.driver;
+		let pins = PinSet::from_bits(self.pin.mask());
+		loop {
+			let seen = driver.notifier.generation();
+			let polarity = {
+				let mut dev = driver.device.lock().await;
+				let mut events = dev.take_interrupt_events(pins);
+				if events.any(|event| accept(&event)) {
+					return Ok(());
+				}
+				dev.interrupt_polarity()
+					.ok_or(Error::InterruptOutputDisabled)?
+			};
+			let mut int = match select(driver.int.lock(), driver.notifier.wait(seen)).await {
+				Either::First(int) => int,
+				Either::Second(()) => continue,
+			};
+			let asserted = async {
+				match polarity {
+					Polarity::High => int.wait_for_high().await,
+					Polarity::Low => int.wait_for_low().await,
+				}
+			};
+			if let Either::First(result) = select(asserted, driver.notifier.wait(seen)).await {
+				result.map_err(|_| Error::InterruptPin)?;
+				driver.device.lock().await.read_interrupts(true).await?;
+				driver.notifier.notify();
+			}
+		}
+	}
+}
+
+/// Waits for the pin's interrupts, through the host pin connected to the interrupt output.
+///
+/// Waiting fails straight away with [`Error::PinMasked`] if the pin's interrupt is masked, and
+/// with [`Error::EdgeFiltered`] if the pin's [`Trigger`] filters out the edge being waited for
+/// (for example [`wait_for_falling_edge`](Wait::wait_for_falling_edge) on a pin which only
+/// reports [`Trigger::Rising`]), as neither would ever finish. [`wait_for_high`](Wait::wait_for_high)
+/// waits for a rising edge, and [`wait_for_low`](Wait::wait_for_low) for a falling one, unless
+/// the pin is already at that level. Masking the pin or changing its trigger while a task is
+/// waiting is not detected.
+impl<'a, E, I2C, P, INT> Wait for Pin<'a, I2C, Interrupt, P, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InputPin + Wait,
+	P: PinIndex,
+	E: Debug,
+{
+	async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+		self.wait_for_level(true).await
+	}
+
+	async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+		self.wait_for_level(false).await
+	}
+
+	async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+		self.wait_for_edge(Some(Edge::Rising)).await
+	}
+
+	async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+		self.wait_for_edge(Some(Edge::Falling)).await
+	}
+
+	async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+		self.wait_for_edge(None).await
+	}
+}
@@ -1,58 +1,327 @@
-use crate::device::{Register, Stmpe1600Device};
-use crate::{Error, PinMode, Polarity, Stmpe1600, DEFAULT_ADDRESS};
-use core::cell::RefCell;
-use embedded_hal::blocking::i2c::{Read, Write};
+#[cfg(feature = "async")]
+use crate::device::Async;
+use crate::device::{Blocking, Register, Stmpe1600Device};
+use crate::{
+	pins_in_mode, Error, InterruptInput, InterruptOutput, NoInterruptPin, PinMode, Polarity,
+	Stmpe1600, DEFAULT_ADDRESS,
+};
+use embedded_hal::digital::{InputPin, PinState};
+use embedded_hal::i2c::I2c;
+#[cfg(feature = "async")]
+use embedded_hal_async::i2c::I2c as AsyncI2c;
 
 /// A builder that allows for configuring all the various options available to edit on the STMPE1600.
-pub struct Stmpe1600Builder<I2C> {
+///
+/// The same builder is used for both the blocking driver ([`build`](Stmpe1600Builder::build)) and,
+/// with the `async` feature enabled, the asynchronous driver
+#[cfg_attr(
+	feature = "async",
+	doc = "([`build_async`](Stmpe1600Builder::build_async))."
+)]
+#[cfg_attr(not(feature = "async"), doc = "(`build_async`).")]
+///
+/// # Initial pin configuration
+/// Every pin starts out as an input. The [`pin_output`](Stmpe1600Builder::pin_output),
+/// [`pin_interrupt`](Stmpe1600Builder::pin_interrupt) and
+/// [`polarity_inversion`](Stmpe1600Builder::polarity_inversion) functions change this, and the
+/// configuration is written to the STMPE1600 when the driver is built. Output levels are set
+/// before any pin is made an output, so outputs never glitch to the wrong level.
+///
+/// ```rust,ignore
+/// use embedded_hal::digital::PinState;
+/// use stmpe1600::{Polarity, Stmpe1600Builder};
+///
+/// let stmpe1600 = Stmpe1600Builder::new(i2c)
+///     .pin_output(0, PinState::High)
+///     .pin_interrupt(1)
+///     .polarity_inversion(1, Polarity::High)
+///     .interrupts(Polarity::Low)
+///     .build()?;
+/// ```
+pub struct Stmpe1600Builder<I2C, INT = NoInterruptPin> {
 	i2c: I2C,
+	int: INT,
 	pins: [PinMode; 16],
+	outputs: u16,
+	inversion: u16,
+	invalid_pin: bool,
 	address: u8,
 	interrupt_polarity: Option<Polarity>,
+	cache: bool,
 }
 
-impl<I2C, E> Stmpe1600Builder<I2C>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
+impl<I2C> Stmpe1600Builder<I2C> {
 	/// Constructs a builder.
 	pub fn new(i2c: I2C) -> Stmpe1600Builder<I2C> {
 		Stmpe1600Builder {
 			i2c,
+			int: NoInterruptPin,
 			pins: [PinMode::Input; 16],
+			outputs: 0,
+			inversion: 0,
+			invalid_pin: false,
 			address: DEFAULT_ADDRESS,
 			interrupt_polarity: None,
+			cache: true,
 		}
 	}
+}
 
+impl<I2C, INT> Stmpe1600Builder<I2C, INT> {
 	/// Sets the I²C address on which to attempt communication with the STMPE1600.
-	pub fn address(mut self, address: u8) -> Stmpe1600Builder<I2C> {
+	pub fn address(mut self, address: u8) -> Self {
 		self.address = address;
 		self
 	}
 
 	/// Enables interrupts, and sets the polarity of the interrupt output pin.
-	pub fn interrupts(mut self, polarity: Polarity) -> Stmpe1600Builder<I2C> {
+	///
+	/// This can also be changed once the driver is built, with
+	/// [`Stmpe1600::enable_interrupt_output`] and [`Stmpe1600::disable_interrupt_output`].
+	pub fn interrupts(mut self, polarity: Polarity) -> Self {
 		self.interrupt_polarity = Some(polarity);
 		self
 	}
 
+	/// Gives the driver the host pin connected to the STMPE1600's interrupt output.
+	///
+	/// The driver reads this pin, and compares it against the polarity set with
+	/// [`interrupts`](Stmpe1600Builder::interrupts), to find out whether an interrupt is pending
+	/// without talking to the STMPE1600. See [`Stmpe1600::interrupt_asserted`].
+	pub fn interrupt_pin<P: InputPin>(self, pin: P) -> Stmpe1600Builder<I2C, P> {
+		Stmpe1600Builder {
+			i2c: self.i2c,
+			int: pin,
+			pins: self.pins,
+			outputs: self.outputs,
+			inversion: self.inversion,
+			invalid_pin: self.invalid_pin,
+			address: self.address,
+			interrupt_polarity: self.interrupt_polarity,
+			cache: self.cache,
+		}
+	}
+
+	/// Configures a pin as an input (the default).
+	///
+	/// If `pin > 15`, [`build`](Stmpe1600Builder::build) will return [`Error::InvalidPin`].
+	pub fn pin_input(mut self, pin: u8) -> Self {
+		if pin >= 16 {
+			self.invalid_pin = true;
+			return self;
+		}
+		self.pins[pin as usize] = PinMode::Input;
+		self
+	}
+
+	/// Configures a pin as an output, which is driven to `state` as soon as the driver is built.
+	///
+	/// If `pin > 15`, [`build`](Stmpe1600Builder::build) will return [`Error::InvalidPin`].
+	///
+	/// The driver can then no longer be [`split`](Stmpe1600::split), which only hands out pins in
+	/// input mode and returns [`Error::PinNotInput`] instead. To split the driver and still have
+	/// the output start at a known level, use [`output_level`](Stmpe1600Builder::output_level).
+	pub fn pin_output(mut self, pin: u8, state: PinState) -> Self {
+		if pin >= 16 {
+			self.invalid_pin = true;
+			return self;
+		}
+		self.pins[pin as usize] = PinMode::Output;
+		self.output_level(pin, state)
+	}
+
+	/// Sets the initial output level of a pin, without changing its mode.
+	///
+	/// The level is written when the driver is built, and is driven as soon as the pin is made an
+	/// output, so a pin can be left as an input (for example to be handed out by
+	/// [`Stmpe1600::split`]) and still start driving the right level.
+	///
+	/// If `pin > 15`, [`build`](Stmpe1600Builder::build) will return [`Error::InvalidPin`].
+	pub fn output_level(mut self, pin: u8, state: PinState) -> Self {
+		if pin >= 16 {
+			self.invalid_pin = true;
+			return self;
+		}
+		match state {
+			PinState::Low => self.outputs &= !(1 << pin),
+			PinState::High => self.outputs |= 1 << pin,
+		}
+		self
+	}
+
+	/// Configures a pin as an interrupt pin.
+	///
+	/// If `pin > 15`, [`build`](Stmpe1600Builder::build) will return [`Error::InvalidPin`].
+	///
+	/// The driver can then no longer be [`split`](Stmpe1600::split), which only hands out pins in
+	/// input mode and returns [`Error::PinNotInput`] instead.
+	pub fn pin_interrupt(mut self, pin: u8) -> Self {
+		if pin >= 16 {
+			self.invalid_pin = true;
+			return self;
+		}
+		self.pins[pin as usize] = PinMode::Interrupt;
+		self
+	}
+
+	/// Sets the initial polarity inversion of a pin.
+	///
+	/// If `pin > 15`, [`build`](Stmpe1600Builder::build) will return [`Error::InvalidPin`].
+	pub fn polarity_inversion(mut self, pin: u8, polarity: Polarity) -> Self {
+		if pin >= 16 {
+			self.invalid_pin = true;
+			return self;
+		}
+		match polarity {
+			Polarity::Low => self.inversion &= !(1 << pin),
+			Polarity::High => self.inversion |= 1 << pin,
+		}
+		self
+	}
+
+	/// Enables or disables the register cache (enabled by default).
+	///
+	/// With the cache enabled, the driver keeps copies of the GPSR, GPDR, GPPIR and IEGPIOR
+	/// registers, so that changing a pin's mode, output level or polarity inversion only needs a
+	/// single write. Disable the cache if anything other than this driver may write to the
+	/// STMPE1600, so that these registers are always read back from the device before being modified.
+	pub fn register_cache(mut self, enabled: bool) -> Self {
+		self.cache = enabled;
+		self
+	}
+
+	/// The register writes needed to apply the initial pin configuration, in the order they must be
+	/// made.
+	///
+	/// The output levels and polarity inversions are written first (and only if they differ from
+	/// their reset values), so that no output glitches and no interrupt fires while the pins are
+	/// being set up. The direction and interrupt enable registers are always written.
+	fn pin_config(&self) -> [Option<(Register, u16)>; 4] {
+		let mask = |mode| pins_in_mode(&self.pins, mode).bits();
+		[
+			Some((Register::GPSR, self.outputs)).filter(|_| self.outputs != 0),
+			Some((Register::GPPIR, self.inversion)).filter(|_| self.inversion != 0),
+			Some((Register::GPDR, mask(PinMode::Output))),
+			Some((Register::IEGPIOR, mask(PinMode::Interrupt))),
+		]
+	}
+}
+
+impl<I2C, E, INT> Stmpe1600Builder<I2C, INT>
+where
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+{
 	/// Consumes the builder, and produces an [`Stmpe1600`](struct.Stmpe1600.html) struct.
-	pub fn build(self) -> Result<Stmpe1600<I2C>, Error<E>> {
-		let mut device = Stmpe1600Device::new(self.i2c, self.address)?;
-
-		if let Some(polarity) = self.interrupt_polarity {
-			let scb = device.read_reg8(Register::SystemControl)?;
-			let polarity = match polarity {
-				Polarity::Low => 0x00,
-				Polarity::High => 0x01,
-			};
-			device.write_reg8(Register::SystemControl, scb | 0x04 | polarity)?;
-		}
-
-		Ok(Stmpe1600 {
-			device: RefCell::new(device),
-			pins: RefCell::new(self.pins),
-		})
+	///
+	/// If this fails, the I²C bus is dropped; use [`try_build`](Stmpe1600Builder::try_build) to
+	/// get it back along with the error.
+	pub fn build(self) -> Result<Stmpe1600<I2C, INT>, Error<E>> {
+		self.try_build().map_err(|(error, ..)| error)
+	}
+
+	/// Consumes the builder, and produces an [`Stmpe1600`](struct.Stmpe1600.html) struct, as
+	/// [`build`](Stmpe1600Builder::build) does.
+	///
+	/// If a pin index given to the builder is out of range, or the STMPE1600 cannot be set up, the
+	/// I²C bus and host interrupt pin are handed back along with the error, as by
+	/// [`Stmpe1600::destroy_all`].
+	pub fn try_build(self) -> Result<Stmpe1600<I2C, INT>, (Error<E>, I2C, INT)> {
+		if self.invalid_pin {
+			return Err((Error::InvalidPin, self.i2c, self.int));
+		}
+		let config = self.pin_config();
+		let mut device =
+			Stmpe1600Device::<I2C, Blocking>::from_parts(self.i2c, self.address, self.cache);
+		match Self::configure(&mut device, config, self.interrupt_polarity) {
+			Ok(()) => {
+				device.set_modes(self.pins);
+				Ok(Stmpe1600::new(device, self.int))
+			}
+			Err(error) => Err((error, device.release(), self.int)),
+		}
+	}
+
+	/// Checks and resets the STMPE1600, then writes the initial configuration.
+	fn configure(
+		device: &mut Stmpe1600Device<I2C, Blocking>,
+		config: [Option<(Register, u16)>; 4],
+		interrupt_polarity: Option<Polarity>,
+	) -> Result<(), Error<E>> {
+		device.init()?;
+
+		for (register, value) in config.iter().flatten() {
+			device.write_reg(*register, *value)?;
+		}
+
+		if let Some(polarity) = interrupt_polarity {
+			device.modify_interrupt_output(|_| InterruptOutput {
+				enabled: true,
+				polarity,
+			})?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<I2C, E, INT> Stmpe1600Builder<I2C, INT>
+where
+	I2C: AsyncI2c<Error = E>,
+	INT: InterruptInput,
+{
+	/// Consumes the builder, and produces an asynchronous [`asynch::Stmpe1600`](crate::asynch::Stmpe1600) struct.
+	///
+	/// If this fails, the I²C bus is dropped; use
+	/// [`try_build_async`](Stmpe1600Builder::try_build_async) to get it back along with the error.
+	pub async fn build_async(self) -> Result<crate::asynch::Stmpe1600<I2C, INT>, Error<E>> {
+		self.try_build_async().await.map_err(|(error, ..)| error)
+	}
+
+	/// Consumes the builder, and produces an asynchronous driver, as
+	/// [`build_async`](Stmpe1600Builder::build_async) does.
+	///
+	/// As with [`try_build`](Stmpe1600Builder::try_build), the I²C bus and host interrupt pin are
+	/// handed back along with the error if this fails.
+	pub async fn try_build_async(
+		self,
+	) -> Result<crate::asynch::Stmpe1600<I2C, INT>, (Error<E>, I2C, INT)> {
+		if self.invalid_pin {
+			return Err((Error::InvalidPin, self.i2c, self.int));
+		}
+		let config = self.pin_config();
+		let mut device =
+			Stmpe1600Device::<I2C, Async>::from_parts(self.i2c, self.address, self.cache);
+		match Self::configure_async(&mut device, config, self.interrupt_polarity).await {
+			Ok(()) => {
+				device.set_modes(self.pins);
+				Ok(crate::asynch::Stmpe1600::new(device, self.int))
+			}
+			Err(error) => Err((error, device.release(), self.int)),
+		}
+	}
+
+	/// Checks and resets the STMPE1600, then writes the initial configuration.
+	async fn configure_async(
+		device: &mut Stmpe1600Device<I2C, Async>,
+		config: [Option<(Register, u16)>; 4],
+		interrupt_polarity: Option<Polarity>,
+	) -> Result<(), Error<E>> {
+		device.init().await?;
+
+		for (register, value) in config.iter().flatten() {
+			device.write_reg(*register, *value).await?;
+		}
+
+		if let Some(polarity) = interrupt_polarity {
+			device
+				.modify_interrupt_output(|_| InterruptOutput {
+					enabled: true,
+					polarity,
+				})
+				.await?;
+		}
+		Ok(())
 	}
 }
@@ -0,0 +1,88 @@
+//! Compatibility with HALs implementing the `embedded-hal` 0.2 traits.
+//!
+//! The driver itself is built on the `embedded-hal` 1.0 [`I2c`] trait. To use it with an I²C bus
+//! that only implements the 0.2 `blocking::i2c::{Read, Write, WriteRead}` traits, wrap the bus in
+//! an [`I2cCompat`] before passing it to the builder.
+//!
+//! ```rust,ignore
+//! use stmpe1600::compat::I2cCompat;
+//! use stmpe1600::Stmpe1600Builder;
+//!
+//! let i2c = /* construct something implementing embedded_hal::blocking::i2c::{Read, Write, WriteRead} (0.2) */;
+//! let stmpe1600 = Stmpe1600Builder::new(I2cCompat::new(i2c)).build()?;
+//! ```
+
+use core::fmt::Debug;
+use embedded_hal::i2c::{self, ErrorKind, ErrorType, I2c, Operation, SevenBitAddress};
+use embedded_hal_02::blocking::i2c::{Read, Write, WriteRead};
+
+/// Adapter which implements the `embedded-hal` 1.0 [`I2c`] trait for an `embedded-hal` 0.2 I²C bus.
+///
+/// The 0.2 traits cannot express arbitrary transactions, so only the sequences of operations
+/// which map onto a single 0.2 call are supported: a lone read, a lone write, and a write followed
+/// by a read (as a repeated start). Any other transaction is rejected with
+/// [`CompatError::Unsupported`] before anything is sent on the bus. The driver only uses the
+/// supported sequences.
+#[derive(Debug)]
+pub struct I2cCompat<I2C> {
+	i2c: I2C,
+}
+
+impl<I2C> I2cCompat<I2C> {
+	/// Wraps an `embedded-hal` 0.2 I²C bus.
+	pub fn new(i2c: I2C) -> I2cCompat<I2C> {
+		I2cCompat { i2c }
+	}
+
+	/// Consumes the adapter, and returns the wrapped I²C bus.
+	pub fn into_inner(self) -> I2C {
+		self.i2c
+	}
+}
+
+/// An error returned by an [`I2cCompat`].
+#[derive(Debug)]
+pub enum CompatError<E> {
+	/// The wrapped `embedded-hal` 0.2 I²C bus returned an error.
+	Bus(E),
+	/// The transaction was made up of a sequence of operations which the 0.2 traits cannot
+	/// perform in one go.
+	Unsupported,
+}
+
+impl<E: Debug> i2c::Error for CompatError<E> {
+	fn kind(&self) -> ErrorKind {
+		ErrorKind::Other
+	}
+}
+
+impl<I2C, E> ErrorType for I2cCompat<I2C>
+where
+	I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+	E: Debug,
+{
+	type Error = CompatError<E>;
+}
+
+impl<I2C, E> I2c for I2cCompat<I2C>
+where
+	I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+	E: Debug,
+{
+	fn transaction(
+		&mut self,
+		address: SevenBitAddress,
+		operations: &mut [Operation<'_>],
+	) -> Result<(), Self::Error> {
+		match operations {
+			[] => Ok(()),
+			[Operation::Read(buffer)] => self.i2c.read(address, buffer),
+			[Operation::Write(bytes)] => self.i2c.write(address, bytes),
+			[Operation::Write(bytes), Operation::Read(buffer)] => {
+				self.i2c.write_read(address, bytes, buffer)
+			}
+			_ => return Err(CompatError::Unsupported),
+		}
+		.map_err(CompatError::Bus)
+	}
+}
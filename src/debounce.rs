@@ -0,0 +1,202 @@
+//! Debouncing of the raw edges of interrupt pins into button presses and releases.
+
+use crate::{PinSet, PinSetIter, TimestampedEvent, TimestampedEvents};
+use core::iter::FusedIterator;
+use embedded_hal::digital::PinState;
+
+/// Whether a button was pressed or released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonAction {
+	/// The pin went high, and stayed high for its debounce window.
+	Press,
+	/// The pin went low, and stayed low for its debounce window.
+	Release,
+}
+
+/// A debounced change of a button, as reported by [`Debouncer::poll`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ButtonEvent {
+	/// The index of the pin which changed.
+	pub pin: u8,
+	/// Whether the button was pressed or released.
+	pub action: ButtonAction,
+	/// The time of the last raw edge of the pin, in ticks of the [`Clock`](crate::Clock), which
+	/// is when the button settled into its new state.
+	pub timestamp: u64,
+}
+
+/// Turns the raw, bouncing edges of interrupt pins into clean [`ButtonEvent`]s.
+///
+/// A pin is pressed while it reads high; for buttons which pull the pin low, enable the pin's
+/// polarity inversion. Each raw edge restarts the pin's debounce window, and once a pin has had no
+/// edges for a whole window, its level is taken as settled. A [`ButtonEvent`] is reported if the
+/// settled level differs from the previous one, so a bounce which ends up back where it started
+/// reports nothing. Every pin starts out released.
+///
+/// As a pin settles without any further edges, the debouncer must be polled again after the
+/// window has passed, even if no interrupt arrives; [`next_deadline`](Debouncer::next_deadline)
+/// says when. [`Stmpe1600::poll_debounced`](crate::Stmpe1600::poll_debounced) reads the pending
+/// interrupts and polls the debouncer in one call. The pins' [`Trigger`](crate::Trigger)s should be
+/// left at [`Trigger::Both`](crate::Trigger::Both), so that the debouncer sees every edge.
+///
+/// ```rust,ignore
+/// use stmpe1600::{ButtonAction, Debouncer};
+///
+/// // A 20 tick window for every pin, with a longer one for a noisy switch on pin 3.
+/// let mut debouncer = Debouncer::new(20);
+/// debouncer.set_window(3, 50);
+///
+/// loop {
+///     for event in stmpe1600.poll_debounced(&mut debouncer, &mut clock)? {
+///         if event.action == ButtonAction::Press {
+///             // Button on `event.pin` was pressed.
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Debouncer {
+	windows: [u64; 16],
+	changed_at: [u64; 16],
+	/// The last raw level of each pin.
+	levels: u16,
+	/// The pins whose last raw edge has not yet settled.
+	unsettled: u16,
+	/// The debounced level of each pin.
+	pressed: u16,
+}
+
+impl Debouncer {
+	/// Creates a debouncer which uses a window of `window` ticks for every pin.
+	pub const fn new(window: u64) -> Debouncer {
+		Debouncer {
+			windows: [window; 16],
+			changed_at: [0; 16],
+			levels: 0,
+			unsettled: 0,
+			pressed: 0,
+		}
+	}
+
+	/// Gets the debounce window of `pin`, in ticks.
+	///
+	/// Returns zero if `pin > 15`.
+	pub fn window(&self, pin: u8) -> u64 {
+		self.windows.get(pin as usize).copied().unwrap_or(0)
+	}
+
+	/// Sets the debounce window of `pin`, in ticks. Pins above 15 are ignored.
+	pub fn set_window(&mut self, pin: u8, window: u64) {
+		if let Some(w) = self.windows.get_mut(pin as usize) {
+			*w = window;
+		}
+	}
+
+	/// The set of pins whose debounced state is pressed.
+	pub fn pressed(&self) -> PinSet {
+		PinSet::from_bits(self.pressed)
+	}
+
+	/// Records a raw edge of a pin, restarting its debounce window.
+	///
+	/// Events for pins above 15 are ignored.
+	pub fn update(&mut self, event: TimestampedEvent) {
+		let pin = event.event.pin;
+		if pin >= 16 {
+			return;
+		}
+		let bit = 1 << pin;
+		match event.event.level {
+			PinState::High => self.levels |= bit,
+			PinState::Low => self.levels &= !bit,
+		}
+		self.unsettled |= bit;
+		self.changed_at[pin as usize] = event.timestamp;
+	}
+
+	/// The time at which the next unsettled pin settles, or `None` if every pin has settled.
+	pub fn next_deadline(&self) -> Option<u64> {
+		PinSet::from_bits(self.unsettled)
+			.iter()
+			.map(|pin| self.deadline(pin))
+			.min()
+	}
+
+	/// Settles every pin whose window has passed at `now`, and reports those whose debounced
+	/// state changed.
+	pub fn poll(&mut self, now: u64) -> ButtonEvents {
+		let mut changed = PinSet::empty();
+		let mut timestamps = [0; 16];
+		for pin in PinSet::from_bits(self.unsettled) {
+			if now < self.deadline(pin) {
+				continue;
+			}
+			let bit = 1 << pin;
+			self.unsettled &= !bit;
+			if (self.levels ^ self.pressed) & bit != 0 {
+				self.pressed ^= bit;
+				changed.insert(pin);
+				timestamps[pin as usize] = self.changed_at[pin as usize];
+			}
+		}
+		ButtonEvents {
+			changed: changed.iter(),
+			pressed: self.pressed,
+			timestamps,
+		}
+	}
+
+	/// Records every edge of `events`, then settles the pins whose windows have passed at `now`.
+	pub(crate) fn settle(&mut self, events: TimestampedEvents, now: u64) -> ButtonEvents {
+		for event in events {
+			self.update(event);
+		}
+		self.poll(now)
+	}
+
+	fn deadline(&self, pin: u8) -> u64 {
+		self.changed_at[pin as usize].saturating_add(self.windows[pin as usize])
+	}
+}
+
+/// An iterator over the [`ButtonEvent`]s reported by one poll of a [`Debouncer`], in ascending
+/// pin order.
+#[derive(Clone, Debug)]
+pub struct ButtonEvents {
+	changed: PinSetIter,
+	pressed: u16,
+	timestamps: [u64; 16],
+}
+
+impl ButtonEvents {
+	/// The pins whose presses or releases are still to be returned.
+	pub fn pending(&self) -> PinSet {
+		self.changed.remaining()
+	}
+}
+
+impl Iterator for ButtonEvents {
+	type Item = ButtonEvent;
+
+	fn next(&mut self) -> Option<ButtonEvent> {
+		let pin = self.changed.next()?;
+		let action = if self.pressed & 1 << pin != 0 {
+			ButtonAction::Press
+		} else {
+			ButtonAction::Release
+		};
+		Some(ButtonEvent {
+			pin,
+			action,
+			timestamp: self.timestamps[pin as usize],
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.changed.size_hint()
+	}
+}
+
+impl ExactSizeIterator for ButtonEvents {}
+
+impl FusedIterator for ButtonEvents {}
@@ -1,10 +1,27 @@
-use crate::Error;
+use crate::interrupts::{Latch, Triggers};
+use crate::{
+	modes_for_direction, pins_in_mode, ChangeDetector, Error, InterruptEvents, InterruptOutput,
+	PinMode, PinSet, Polarity, Trigger,
+};
 use core::fmt::Debug;
-use embedded_hal::blocking::i2c::{Read, Write};
+use core::marker::PhantomData;
+use embedded_hal::i2c::I2c;
+#[cfg(feature = "async")]
+use embedded_hal_async::i2c::I2c as AsyncI2c;
 
 const DEVICE_ID: u16 = 0x1600;
 
-#[allow(non_camel_case_types, dead_code)]
+/// Value written to the SystemControl register to perform a software reset.
+const SOFT_RESET: u8 = 0x80;
+
+/// SystemControl bit which enables the interrupt output pin.
+const INT_ENABLE: u8 = 0x04;
+
+/// SystemControl bit which makes the interrupt output pin active high.
+const INT_POLARITY: u8 = 0x01;
+
+#[allow(non_camel_case_types, dead_code, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
 #[repr(u8)]
 /// The different adresses of the registers on the STMPE1600's I²C bus.
 pub enum Register {
@@ -26,78 +43,470 @@ pub enum Register {
 	GPPIR = 0x16,
 }
 
+/// Marker for a device accessed through the blocking `embedded-hal` I²C traits.
+#[derive(Debug)]
+pub(crate) struct Blocking;
+
+/// Marker for a device accessed through the `embedded-hal-async` I²C traits.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub(crate) struct Async;
+
+/// Shadow copies of the writable GPIO registers.
+///
+/// All of these registers are cleared by a software reset, so the shadow starts out zeroed once
+/// the device has been reset.
+#[derive(Clone, Copy, Debug, Default)]
+struct Shadow {
+	iegpior: u16,
+	gpsr: u16,
+	gpdr: u16,
+	gppir: u16,
+}
+
+impl Shadow {
+	fn get(&self, register: Register) -> Option<u16> {
+		match register {
+			Register::IEGPIOR => Some(self.iegpior),
+			Register::GPSR => Some(self.gpsr),
+			Register::GPDR => Some(self.gpdr),
+			Register::GPPIR => Some(self.gppir),
+			_ => None,
+		}
+	}
+
+	fn set(&mut self, register: Register, value: u16) {
+		match register {
+			Register::IEGPIOR => self.iegpior = value,
+			Register::GPSR => self.gpsr = value,
+			Register::GPDR => self.gpdr = value,
+			Register::GPPIR => self.gppir = value,
+			_ => {}
+		}
+	}
+}
+
+/// The register-level interface to the STMPE1600.
+///
+/// `M` selects whether the bus is accessed through blocking or asynchronous traits. Everything
+/// apart from the bus transfers themselves is shared between the two.
+///
+/// When the register cache is enabled, writes to the GPIO registers are mirrored in a [`Shadow`],
+/// and read-modify-write operations are served from it instead of reading the register back.
 #[derive(Debug)]
-pub(crate) struct Stmpe1600Device<I2C> {
+pub(crate) struct Stmpe1600Device<I2C, M = Blocking> {
 	i2c: I2C,
 	address: u8,
+	cache: bool,
+	shadow: Option<Shadow>,
+	modes: [PinMode; 16],
+	triggers: Triggers,
+	latch: Latch,
+	int_output: Option<Polarity>,
+	_mode: PhantomData<M>,
 }
 
-impl<I2C, E> Stmpe1600Device<I2C>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
-	pub fn new(i2c: I2C, address: u8) -> Result<Stmpe1600Device<I2C>, Error<E>> {
-		let mut device = Stmpe1600Device { i2c, address };
-		device.init()?;
-		Ok(device)
+impl<I2C, M> Stmpe1600Device<I2C, M> {
+	/// Creates the device without talking to the STMPE1600.
+	pub fn from_parts(i2c: I2C, address: u8, cache: bool) -> Stmpe1600Device<I2C, M> {
+		Stmpe1600Device {
+			i2c,
+			address,
+			cache,
+			shadow: None,
+			modes: [PinMode::Input; 16],
+			triggers: Triggers::default(),
+			latch: Latch::default(),
+			int_output: None,
+			_mode: PhantomData,
+		}
 	}
 
-	pub fn read_reg(&mut self, register: Register) -> Result<u16, Error<E>> {
-		self.i2c
-			.write(self.address, &[register as u8])
-			.map_err(Error::I2CError)?;
-		let mut buffer = [0u8; 2];
+	/// Consumes the device, and returns the I²C bus.
+	pub fn release(self) -> I2C {
 		self.i2c
-			.read(self.address, &mut buffer)
-			.map_err(Error::I2CError)?;
-		Ok((buffer[1] as u16) << 8 | buffer[0] as u16)
 	}
 
-	pub fn read_reg8(&mut self, register: Register) -> Result<u8, Error<E>> {
-		self.i2c
-			.write(self.address, &[register as u8])
-			.map_err(Error::I2CError)?;
-		let mut buffer = [0u8];
-		self.i2c
-			.read(self.address, &mut buffer)
-			.map_err(Error::I2CError)?;
-		Ok(buffer[0])
+	/// Called once the device has been reset, when every cached register is known to be zero and
+	/// every pin is an input.
+	fn reset_shadow(&mut self) {
+		if self.cache {
+			self.shadow = Some(Shadow::default());
+		}
+		self.modes = [PinMode::Input; 16];
+		self.triggers = Triggers::default();
+		self.latch = Latch::default();
+		self.int_output = None;
 	}
 
-	pub fn write_reg(&mut self, register: Register, value: u16) -> Result<(), Error<E>> {
-		self.i2c
-			.write(
-				self.address,
-				&[register as u8, value as u8, (value >> 8) as u8],
-			)
-			.map_err(Error::I2CError)
+	/// The mode each pin is configured in.
+	pub fn modes(&self) -> &[PinMode; 16] {
+		&self.modes
 	}
 
-	pub fn write_reg8(&mut self, register: Register, value: u8) -> Result<(), Error<E>> {
-		self.i2c
-			.write(self.address, &[register as u8, value as u8])
-			.map_err(Error::I2CError)
+	/// Records the pin modes, once the builder has written them to the STMPE1600.
+	pub fn set_modes(&mut self, modes: [PinMode; 16]) {
+		self.modes = modes;
 	}
 
-	pub fn get_interrupts(&mut self) -> Result<[bool; 16], Error<E>> {
-		let mask = self.read_reg(Register::ISGPIOR)?;
-		let mut arr = [false; 16];
-		for i in 0..16 {
-			if mask & 1 << i == 1 << i {
-				arr[i] = true;
-			}
+	/// The set of pins which are configured as interrupt pins.
+	fn interrupt_pins(&self) -> PinSet {
+		pins_in_mode(&self.modes, PinMode::Interrupt)
+	}
+
+	/// The level the interrupt output pin is driven to while an interrupt is pending, or `None` if
+	/// it is disabled.
+	pub fn interrupt_polarity(&self) -> Option<Polarity> {
+		self.int_output
+	}
+
+	/// Gets the edges which are reported for a pin.
+	pub fn trigger(&self, pin: u8) -> Trigger {
+		self.triggers.get(pin)
+	}
+
+	/// Sets the edges which are reported for a pin.
+	pub fn set_trigger(&mut self, pin: u8, trigger: Trigger) {
+		self.triggers.set(pin, trigger);
+	}
+
+	/// Turns the pins in `changed` into events, as if they had raised interrupts, removing those
+	/// for edges which are not reported.
+	///
+	/// Only the levels of the reported pins are kept, as for the latched interrupts.
+	pub fn change_events(&self, changed: u16, levels: u16) -> InterruptEvents {
+		let pending = self.triggers.filter(changed, levels);
+		InterruptEvents::new(pending, levels & pending)
+	}
+
+	/// Clears the latched interrupt of a pin, without reading anything from the STMPE1600.
+	pub fn clear_pending(&mut self, pin: u8) {
+		self.latch.clear(1 << pin);
+	}
+
+	/// Whether the pin levels need to be read along with the interrupt status `pending`.
+	fn levels_needed(&self, pending: u16, levels: bool) -> bool {
+		pending != 0 && (levels || self.triggers.needs_levels(pending))
+	}
+
+	fn cached(&self, register: Register) -> Option<u16> {
+		self.shadow.as_ref().and_then(|shadow| shadow.get(register))
+	}
+
+	fn store(&mut self, register: Register, value: u16) {
+		if let Some(shadow) = self.shadow.as_mut() {
+			shadow.set(register, value);
 		}
-		Ok(arr)
 	}
 
-	fn init(&mut self) -> Result<(), Error<E>> {
-		if self.read_reg(Register::ChipID)? != DEVICE_ID {
+	fn check_id<E>(id: u16) -> Result<(), Error<E>> {
+		if id != DEVICE_ID {
 			return Err(Error::InvalidDeviceID);
 		}
+		Ok(())
+	}
 
-		// Do a software reset
-		self.write_reg8(Register::SystemControl, 0x80)?;
+	fn decode(buffer: [u8; 2]) -> u16 {
+		(buffer[1] as u16) << 8 | buffer[0] as u16
+	}
 
-		Ok(())
+	fn encode(register: Register, value: u16) -> [u8; 3] {
+		[register as u8, value as u8, (value >> 8) as u8]
+	}
+
+	/// The new value of GPSR `gpsr` once the pins in `set` are driven high and those in `clear`
+	/// are driven low. Pins in both sets are driven high.
+	fn outputs(gpsr: u16, set: PinSet, clear: PinSet) -> u16 {
+		gpsr & !clear.bits() | set.bits()
 	}
+
+	fn update(value: u16, mask: u16, set: bool) -> u16 {
+		if set {
+			value | mask
+		} else {
+			value & !mask
+		}
+	}
+
+	fn decode_interrupt_output(scb: u8) -> InterruptOutput {
+		InterruptOutput {
+			enabled: scb & INT_ENABLE != 0,
+			polarity: match scb & INT_POLARITY {
+				0 => Polarity::Low,
+				_ => Polarity::High,
+			},
+		}
+	}
+
+	/// Computes the new value of the SystemControl register needed to configure the interrupt
+	/// output pin, leaving any other bits untouched.
+	fn encode_interrupt_output(scb: u8, output: InterruptOutput) -> u8 {
+		let mut scb = scb & !(SOFT_RESET | INT_ENABLE | INT_POLARITY);
+		if output.enabled {
+			scb |= INT_ENABLE;
+		}
+		if output.polarity == Polarity::High {
+			scb |= INT_POLARITY;
+		}
+		scb
+	}
+
+	/// Works out the register changes needed to move a pin from one mode to another, as the
+	/// register and whether the pin's bit is set. They must be applied in order.
+	///
+	/// A pin's interrupt is only ever enabled while it is an input, so it is disabled before the
+	/// direction changes, and enabled after.
+	fn mode_transition(from: PinMode, to: PinMode) -> [Option<(Register, bool)>; 2] {
+		let gpdr = Some((Register::GPDR, to == PinMode::Output))
+			.filter(|_| (from == PinMode::Output) != (to == PinMode::Output));
+		let iegpior = Some((Register::IEGPIOR, to == PinMode::Interrupt))
+			.filter(|_| (from == PinMode::Interrupt) != (to == PinMode::Interrupt));
+		match from {
+			PinMode::Interrupt => [iegpior, gpdr],
+			_ => [gpdr, iegpior],
+		}
+	}
+
+	/// Called once the registers have been updated for a change of pin mode. A pin which becomes
+	/// an interrupt pin starts out reporting both edges, with nothing pending.
+	fn mode_changed(&mut self, pin: u8, from: PinMode, to: PinMode) {
+		self.modes[pin as usize] = to;
+		if (from == PinMode::Interrupt) != (to == PinMode::Interrupt) {
+			self.triggers.set(pin, Trigger::Both);
+			self.latch.clear(1 << pin);
+		}
+	}
+
+	/// Called once the registers have been updated for a change of direction, as
+	/// [`mode_changed`](Stmpe1600Device::mode_changed) is for each pin.
+	fn modes_changed(&mut self, modes: [PinMode; 16]) {
+		for (pin, &to) in modes.iter().enumerate() {
+			self.mode_changed(pin as u8, self.modes[pin], to);
+		}
+	}
+
+	/// Adds the interrupt status `pending` and pin `levels` which were just read to the latch,
+	/// filtering out unwanted edges.
+	fn latch_interrupts(&mut self, pending: u16, levels: u16) {
+		self.latch
+			.add(self.triggers.filter(pending, levels), levels);
+	}
+
+	/// Takes every latched interrupt.
+	pub fn take_interrupts(&mut self) -> PinSet {
+		let (pending, _) = self.latch.take(0xFFFF);
+		PinSet::from_bits(pending)
+	}
+
+	/// Takes the latched interrupts of `pins`, with the pin levels.
+	pub fn take_interrupt_events(&mut self, pins: PinSet) -> InterruptEvents {
+		let (pending, levels) = self.latch.take(pins.bits());
+		InterruptEvents::new(pending, levels)
+	}
+
+	/// Whether a pin has a latched interrupt.
+	pub fn is_latched(&self, pin: u8) -> bool {
+		self.latch.is_pending(1 << pin)
+	}
+
+	/// Works out the new value of the SystemControl register `scb` once `f` has been applied to
+	/// the interrupt output configuration, and the interrupt polarity to record once it is
+	/// written.
+	fn update_interrupt_output<F>(scb: u8, f: F) -> (u8, Option<Polarity>)
+	where
+		F: FnOnce(InterruptOutput) -> InterruptOutput,
+	{
+		let output = f(Self::decode_interrupt_output(scb));
+		(
+			Self::encode_interrupt_output(scb, output),
+			Some(output.polarity).filter(|_| output.enabled),
+		)
+	}
+}
+
+/// Implements the operations which talk to the STMPE1600, for one way of accessing the bus.
+///
+/// The blocking and async impls are both generated from this one definition, so they only differ
+/// in whether the functions are `async`, and whether the bus calls are awaited. Everything else is
+/// done by the helpers shared between them above.
+macro_rules! impl_bus {
+	($mode:ty, [$($bound:tt)+], [$($async:tt)?], [$($await:tt)*]) => {
+		impl<I2C, E> Stmpe1600Device<I2C, $mode>
+		where
+			I2C: $($bound)+,
+		{
+			/// Reads from `register` onwards, as a single transfer with a repeated start, so that
+			/// nothing else sharing the bus can move the STMPE1600's register pointer before the
+			/// data is read.
+			$($async)? fn read(&mut self, register: Register, buffer: &mut [u8]) -> Result<(), Error<E>> {
+				self.i2c
+					.write_read(self.address, &[register as u8], buffer)
+					$($await)*
+					.map_err(Error::I2CError)
+			}
+
+			$($async)? fn write(&mut self, bytes: &[u8]) -> Result<(), Error<E>> {
+				self.i2c
+					.write(self.address, bytes)
+					$($await)*
+					.map_err(Error::I2CError)
+			}
+
+			pub $($async)? fn read_reg(&mut self, register: Register) -> Result<u16, Error<E>> {
+				let mut buffer = [0u8; 2];
+				self.read(register, &mut buffer)$($await)*?;
+				Ok(Self::decode(buffer))
+			}
+
+			pub $($async)? fn read_reg8(&mut self, register: Register) -> Result<u8, Error<E>> {
+				let mut buffer = [0u8];
+				self.read(register, &mut buffer)$($await)*?;
+				Ok(buffer[0])
+			}
+
+			/// Reads a register, using its cached value if there is one.
+			pub $($async)? fn read_reg_cached(&mut self, register: Register) -> Result<u16, Error<E>> {
+				match self.cached(register) {
+					Some(value) => Ok(value),
+					None => self.read_reg(register)$($await)*,
+				}
+			}
+
+			pub $($async)? fn write_reg(&mut self, register: Register, value: u16) -> Result<(), Error<E>> {
+				self.write(&Self::encode(register, value))$($await)*?;
+				self.store(register, value);
+				Ok(())
+			}
+
+			pub $($async)? fn write_reg8(&mut self, register: Register, value: u8) -> Result<(), Error<E>> {
+				self.write(&[register as u8, value])$($await)*
+			}
+
+			/// Applies `f` to the current value of a register, and writes back the result.
+			pub $($async)? fn modify_reg<F>(&mut self, register: Register, f: F) -> Result<(), Error<E>>
+			where
+				F: FnOnce(u16) -> u16,
+			{
+				let value = self.read_reg_cached(register)$($await)*?;
+				self.write_reg(register, f(value))$($await)*
+			}
+
+			/// Sets or clears the bits in `mask` of a register, leaving the other bits untouched.
+			pub $($async)? fn update_reg(
+				&mut self,
+				register: Register,
+				mask: u16,
+				set: bool,
+			) -> Result<(), Error<E>> {
+				self.modify_reg(register, |value| Self::update(value, mask, set))$($await)*
+			}
+
+			/// Changes the mode of a pin, only touching the registers which need to change.
+			pub $($async)? fn set_mode(&mut self, pin: u8, mode: PinMode) -> Result<(), Error<E>> {
+				let from = self.modes[pin as usize];
+				for &(register, set) in Self::mode_transition(from, mode).iter().flatten() {
+					self.update_reg(register, 1 << pin, set)$($await)*?;
+				}
+				self.mode_changed(pin, from, mode);
+				Ok(())
+			}
+
+			/// Sets the direction of all 16 pins, making the pins in `outputs` outputs and all
+			/// others inputs, and disabling the interrupts of interrupt pins which become outputs.
+			pub $($async)? fn set_direction(&mut self, outputs: PinSet) -> Result<(), Error<E>> {
+				let (modes, disable) = modes_for_direction(&self.modes, outputs.bits());
+				if disable != 0 {
+					self.update_reg(Register::IEGPIOR, disable, false)$($await)*?;
+				}
+				self.write_reg(Register::GPDR, outputs.bits())$($await)*?;
+				self.modes_changed(modes);
+				Ok(())
+			}
+
+			/// Gets the set of interrupt pins whose interrupts are masked.
+			pub $($async)? fn interrupt_mask(&mut self) -> Result<PinSet, Error<E>> {
+				let enabled = self.read_reg_cached(Register::IEGPIOR)$($await)*?;
+				Ok(self.interrupt_pins() - PinSet::from_bits(enabled))
+			}
+
+			/// Masks the interrupts of the interrupt pins in `masked`, and unmasks the rest.
+			pub $($async)? fn set_interrupt_mask(&mut self, masked: PinSet) -> Result<(), Error<E>> {
+				let enabled = self.interrupt_pins() - masked;
+				self.write_reg(Register::IEGPIOR, enabled.bits())$($await)*
+			}
+
+			/// Drives the pins in `set` high and the pins in `clear` low, in a single write.
+			pub $($async)? fn set_outputs(&mut self, set: PinSet, clear: PinSet) -> Result<(), Error<E>> {
+				self.modify_reg(Register::GPSR, |gpsr| Self::outputs(gpsr, set, clear))$($await)*
+			}
+
+			/// Toggles the output state of the pins in `pins`, in a single write.
+			pub $($async)? fn toggle_outputs(&mut self, pins: PinSet) -> Result<(), Error<E>> {
+				self.modify_reg(Register::GPSR, |gpsr| gpsr ^ pins.bits())$($await)*
+			}
+
+			/// Reads the pin states, and turns the pins watched by `detector` which changed into
+			/// events.
+			pub $($async)? fn poll_changes(
+				&mut self,
+				detector: &mut ChangeDetector,
+			) -> Result<InterruptEvents, Error<E>> {
+				let levels = self.read_reg(Register::GPMR)$($await)*?;
+				let changed = detector.update(PinSet::from_bits(levels));
+				Ok(self.change_events(changed, levels))
+			}
+
+			/// Reads the pending interrupts into the latch, filtering out unwanted edges.
+			///
+			/// The pin levels are read as well if any interrupts are pending, and either `levels`
+			/// is set or they are needed for the filtering.
+			pub $($async)? fn read_interrupts(&mut self, levels: bool) -> Result<(), Error<E>> {
+				let pending = self.read_reg(Register::ISGPIOR)$($await)*?;
+				let levels = match self.levels_needed(pending, levels) {
+					true => self.read_reg(Register::GPMR)$($await)*?,
+					false => 0,
+				};
+				self.latch_interrupts(pending, levels);
+				Ok(())
+			}
+
+			/// Reads the configuration of the interrupt output pin.
+			pub $($async)? fn interrupt_output(&mut self) -> Result<InterruptOutput, Error<E>> {
+				let scb = self.read_reg8(Register::SystemControl)$($await)*?;
+				Ok(Self::decode_interrupt_output(scb))
+			}
+
+			/// Applies `f` to the configuration of the interrupt output pin, and writes back the
+			/// result.
+			pub $($async)? fn modify_interrupt_output<F>(&mut self, f: F) -> Result<(), Error<E>>
+			where
+				F: FnOnce(InterruptOutput) -> InterruptOutput,
+			{
+				let scb = self.read_reg8(Register::SystemControl)$($await)*?;
+				let (scb, polarity) = Self::update_interrupt_output(scb, f);
+				self.write_reg8(Register::SystemControl, scb)$($await)*?;
+				self.int_output = polarity;
+				Ok(())
+			}
+
+			/// Checks that the device is an STMPE1600, then resets it.
+			pub $($async)? fn init(&mut self) -> Result<(), Error<E>> {
+				Self::check_id(self.read_reg(Register::ChipID)$($await)*?)?;
+
+				// Do a software reset
+				self.reset()$($await)*
+			}
+
+			/// Performs a software reset, returning every register to its default value.
+			pub $($async)? fn reset(&mut self) -> Result<(), Error<E>> {
+				self.write_reg8(Register::SystemControl, SOFT_RESET)$($await)*?;
+				self.reset_shadow();
+				Ok(())
+			}
+		}
+	};
 }
+
+impl_bus!(Blocking, [I2c<Error = E>], [], []);
+#[cfg(feature = "async")]
+impl_bus!(Async, [AsyncI2c<Error = E>], [async], [.await]);
@@ -0,0 +1,280 @@
+//! Detection of clicks, double clicks, long presses and repeats from debounced button events.
+
+use crate::{ButtonAction, ButtonEvent, PinSet, PinSetIter};
+use core::iter::FusedIterator;
+
+/// A gesture made with a button, as reported by a [`GestureDetector`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+	/// The button was pressed and released, and not pressed again within the double click window.
+	Click,
+	/// The button was clicked twice, with the second press inside the double click window.
+	DoubleClick,
+	/// The button has been held for the long press time.
+	LongPress,
+	/// The button is still held, one repeat interval after the long press or the last repeat.
+	Repeat,
+}
+
+/// A gesture made with the button on a pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GestureEvent {
+	/// The index of the pin the button is on.
+	pub pin: u8,
+	/// The gesture which was made.
+	pub gesture: Gesture,
+	/// When the gesture was made, in ticks of the [`Clock`](crate::Clock): the time of the release
+	/// for clicks and double clicks, and the time the button had been held long enough for long
+	/// presses and repeats.
+	pub timestamp: u64,
+}
+
+/// The timings of the gestures of a pin, in ticks of the [`Clock`](crate::Clock).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GestureConfig {
+	/// The longest gap between releasing the button and pressing it again for the two clicks to
+	/// count as a double click. With zero, double clicks are never reported, and clicks are
+	/// reported as soon as the button is released.
+	pub double_click: u64,
+	/// How long the button must be held to make a long press, or `None` to never report long
+	/// presses. A long press is not also reported as a click when the button is released.
+	pub long_press: Option<u64>,
+	/// The interval between repeats while the button is still held after a long press, or `None`
+	/// to never report repeats.
+	pub repeat: Option<u64>,
+}
+
+/// The state of the button on a single pin.
+#[derive(Clone, Copy, Debug)]
+enum ButtonState {
+	Idle,
+	/// The button is held. `second` is set if this press follows a click within the double click
+	/// window, and `held` once a long press has been reported. `next` is when the next long press
+	/// or repeat is due.
+	Pressed {
+		second: bool,
+		held: bool,
+		next: Option<u64>,
+	},
+	/// The button was clicked at `at`, and may still be pressed again for a double click.
+	Released {
+		at: u64,
+	},
+}
+
+/// Recognises [`Gesture`]s from the debounced [`ButtonEvent`]s of each pin.
+///
+/// Button events are passed to [`update`](GestureDetector::update), which reports the gestures
+/// completed by a press or release. Gestures which complete with the passing of time (a click
+/// which is not followed by a second one, long presses and repeats) are reported by
+/// [`poll`](GestureDetector::poll), which must be called again once
+/// [`next_deadline`](GestureDetector::next_deadline) has passed.
+///
+/// Each pin has its own [`GestureConfig`]. All of the state is kept in fixed-size arrays, so the
+/// detector never allocates. A click followed by a long press only reports the long press.
+///
+/// ```rust,ignore
+/// use stmpe1600::{Debouncer, Gesture, GestureConfig, GestureDetector};
+///
+/// let mut debouncer = Debouncer::new(20);
+/// let mut gestures = GestureDetector::new(GestureConfig {
+///     double_click: 300,
+///     long_press: Some(1000),
+///     repeat: Some(200),
+/// });
+///
+/// loop {
+///     for event in stmpe1600.poll_debounced(&mut debouncer, &mut clock)? {
+///         if let Some(gesture) = gestures.update(event) {
+///             // ...
+///         }
+///     }
+///     for gesture in gestures.poll(clock.now()) {
+///         // ...
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GestureDetector {
+	configs: [GestureConfig; 16],
+	states: [ButtonState; 16],
+}
+
+impl GestureDetector {
+	/// Creates a detector which uses `config` for every pin.
+	pub const fn new(config: GestureConfig) -> GestureDetector {
+		GestureDetector {
+			configs: [config; 16],
+			states: [ButtonState::Idle; 16],
+		}
+	}
+
+	/// Gets the configuration of `pin`, or `None` if `pin > 15`.
+	pub fn config(&self, pin: u8) -> Option<GestureConfig> {
+		self.configs.get(pin as usize).copied()
+	}
+
+	/// Sets the configuration of `pin`. Pins above 15 are ignored.
+	///
+	/// A gesture which is in progress on the pin carries on with its old timings.
+	pub fn set_config(&mut self, pin: u8, config: GestureConfig) {
+		if let Some(c) = self.configs.get_mut(pin as usize) {
+			*c = config;
+		}
+	}
+
+	/// Records a debounced press or release, and returns the gesture it completes, if any.
+	///
+	/// Events for pins above 15 are ignored.
+	pub fn update(&mut self, event: ButtonEvent) -> Option<GestureEvent> {
+		let pin = event.pin;
+		let config = self.config(pin)?;
+		let t = event.timestamp;
+		let pressed = |second| ButtonState::Pressed {
+			second,
+			held: false,
+			next: config
+				.long_press
+				.map(|long_press| t.saturating_add(long_press)),
+		};
+		let state = &mut self.states[pin as usize];
+		let (next, gesture) = match (*state, event.action) {
+			(ButtonState::Idle, ButtonAction::Press) => (pressed(false), None),
+			(ButtonState::Released { at }, ButtonAction::Press) => {
+				if t < at.saturating_add(config.double_click) {
+					(pressed(true), None)
+				} else {
+					(pressed(false), Some((Gesture::Click, at)))
+				}
+			}
+			(ButtonState::Pressed { held: true, .. }, ButtonAction::Release) => {
+				(ButtonState::Idle, None)
+			}
+			(ButtonState::Pressed { second: true, .. }, ButtonAction::Release) => {
+				(ButtonState::Idle, Some((Gesture::DoubleClick, t)))
+			}
+			(ButtonState::Pressed { .. }, ButtonAction::Release) if config.double_click == 0 => {
+				(ButtonState::Idle, Some((Gesture::Click, t)))
+			}
+			(ButtonState::Pressed { .. }, ButtonAction::Release) => {
+				(ButtonState::Released { at: t }, None)
+			}
+			// A press while already pressed, or a release while already released.
+			(unchanged, _) => (unchanged, None),
+		};
+		*state = next;
+		gesture.map(|(gesture, timestamp)| GestureEvent {
+			pin,
+			gesture,
+			timestamp,
+		})
+	}
+
+	/// The time at which the next gesture may complete without any further button events, or
+	/// `None` if no gesture is in progress.
+	pub fn next_deadline(&self) -> Option<u64> {
+		(0..16).filter_map(|pin| self.deadline(pin)).min()
+	}
+
+	/// Reports the gestures which have completed by `now` without any further button events.
+	///
+	/// At most one gesture is reported for each pin. If repeats are overdue because the detector
+	/// was not polled in time, only one is reported, and the rest are skipped. Once the next repeat
+	/// would be beyond the largest time the clock can reach, no more repeats are reported.
+	pub fn poll(&mut self, now: u64) -> GestureEvents {
+		let mut pending = PinSet::empty();
+		let mut gestures = [Gesture::Click; 16];
+		let mut timestamps = [0; 16];
+		for pin in 0..16 {
+			let due = match self.deadline(pin) {
+				Some(due) if due <= now => due,
+				_ => continue,
+			};
+			let config = self.configs[pin as usize];
+			let state = &mut self.states[pin as usize];
+			let gesture = match state {
+				ButtonState::Released { at } => {
+					let at = *at;
+					*state = ButtonState::Idle;
+					timestamps[pin as usize] = at;
+					Gesture::Click
+				}
+				ButtonState::Pressed { held, next, .. } => {
+					let gesture = if *held {
+						Gesture::Repeat
+					} else {
+						Gesture::LongPress
+					};
+					*held = true;
+					// The first repeat after `now`, skipping any which are overdue.
+					*next = config.repeat.and_then(|repeat| {
+						let repeat = repeat.max(1);
+						let skipped = (now - due) / repeat;
+						skipped
+							.checked_add(1)?
+							.checked_mul(repeat)?
+							.checked_add(due)
+					});
+					timestamps[pin as usize] = due;
+					gesture
+				}
+				ButtonState::Idle => continue,
+			};
+			pending.insert(pin);
+			gestures[pin as usize] = gesture;
+		}
+		GestureEvents {
+			pending: pending.iter(),
+			gestures,
+			timestamps,
+		}
+	}
+
+	/// When the gesture in progress on `pin` completes if nothing else happens.
+	fn deadline(&self, pin: u8) -> Option<u64> {
+		match self.states[pin as usize] {
+			ButtonState::Idle => None,
+			ButtonState::Pressed { next, .. } => next,
+			ButtonState::Released { at } => {
+				Some(at.saturating_add(self.configs[pin as usize].double_click))
+			}
+		}
+	}
+}
+
+/// An iterator over the [`GestureEvent`]s reported by one poll of a [`GestureDetector`], in
+/// ascending pin order.
+#[derive(Clone, Debug)]
+pub struct GestureEvents {
+	pending: PinSetIter,
+	gestures: [Gesture; 16],
+	timestamps: [u64; 16],
+}
+
+impl GestureEvents {
+	/// The pins with a gesture still to be returned.
+	pub fn pending(&self) -> PinSet {
+		self.pending.remaining()
+	}
+}
+
+impl Iterator for GestureEvents {
+	type Item = GestureEvent;
+
+	fn next(&mut self) -> Option<GestureEvent> {
+		let pin = self.pending.next()?;
+		Some(GestureEvent {
+			pin,
+			gesture: self.gestures[pin as usize],
+			timestamp: self.timestamps[pin as usize],
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.pending.size_hint()
+	}
+}
+
+impl ExactSizeIterator for GestureEvents {}
+
+impl FusedIterator for GestureEvents {}
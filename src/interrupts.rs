@@ -0,0 +1,290 @@
+//! Interrupt events, decoded from the interrupt status and pin state registers.
+
+use crate::{Error, PinSet, PinSetIter, Polarity};
+use core::cell::Cell;
+use core::iter::FusedIterator;
+use embedded_hal::digital::{InputPin, PinState};
+
+/// The direction in which an interrupt pin changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+	/// The pin went from low to high.
+	Rising,
+	/// The pin went from high to low.
+	Falling,
+}
+
+/// The configuration of the STMPE1600's interrupt output pin, as read by
+/// [`Stmpe1600::interrupt_output`](crate::Stmpe1600::interrupt_output).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterruptOutput {
+	/// Whether the interrupt output pin is driven when an interrupt is pending.
+	pub enabled: bool,
+	/// The level the interrupt output pin is driven to when an interrupt is pending.
+	pub polarity: Polarity,
+}
+
+/// Used in place of a host pin when the STMPE1600's interrupt output is not connected to the
+/// driver with [`Stmpe1600Builder::interrupt_pin`](crate::Stmpe1600Builder::interrupt_pin).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoInterruptPin;
+
+mod sealed {
+	pub trait Sealed {}
+}
+
+impl sealed::Sealed for NoInterruptPin {}
+impl<T: InputPin> sealed::Sealed for T {}
+
+/// The host pin connected to the STMPE1600's interrupt output.
+///
+/// This is implemented for every [`InputPin`], and for [`NoInterruptPin`] when no pin has been
+/// given to the builder, and cannot be implemented outside of this crate.
+pub trait InterruptInput: sealed::Sealed {
+	/// Reads whether the pin is high, or returns `None` if there is no pin.
+	#[doc(hidden)]
+	fn read_high(&mut self) -> Option<Result<bool, ()>>;
+}
+
+impl InterruptInput for NoInterruptPin {
+	fn read_high(&mut self) -> Option<Result<bool, ()>> {
+		None
+	}
+}
+
+impl<T: InputPin> InterruptInput for T {
+	fn read_high(&mut self) -> Option<Result<bool, ()>> {
+		Some(self.is_high().map_err(|_| ()))
+	}
+}
+
+/// Whether the interrupt output, which is driven to `polarity` while an interrupt is pending, is
+/// asserted according to the host pin `int`. Without a host pin, it is assumed to be.
+pub(crate) fn is_asserted<INT, E>(int: &mut INT, polarity: Polarity) -> Result<bool, Error<E>>
+where
+	INT: InterruptInput,
+{
+	match int.read_high() {
+		Some(Ok(high)) => Ok(high == (polarity == Polarity::High)),
+		Some(Err(())) => Err(Error::InterruptPin),
+		None => Ok(true),
+	}
+}
+
+/// The edges of an interrupt pin which are reported, as set by
+/// [`Pin::set_trigger`](crate::Pin::set_trigger).
+///
+/// The STMPE1600 raises an interrupt on every change of an interrupt pin, so the unwanted edges
+/// are filtered out by the driver when the pending interrupts are read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Trigger {
+	/// Only report the pin going from low to high.
+	Rising,
+	/// Only report the pin going from high to low.
+	Falling,
+	/// Report every change of the pin (the default).
+	#[default]
+	Both,
+}
+
+#[cfg(feature = "async")]
+impl Trigger {
+	/// Whether `edge` is reported by a pin with this trigger.
+	pub(crate) fn reports(self, edge: Edge) -> bool {
+		!matches!(
+			(self, edge),
+			(Trigger::Rising, Edge::Falling) | (Trigger::Falling, Edge::Rising)
+		)
+	}
+}
+
+/// The [`Trigger`] of every pin, stored as the masks of pins which report each edge.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Triggers {
+	rising: u16,
+	falling: u16,
+}
+
+impl Default for Triggers {
+	fn default() -> Triggers {
+		Triggers {
+			rising: 0xFFFF,
+			falling: 0xFFFF,
+		}
+	}
+}
+
+impl Triggers {
+	pub fn get(&self, pin: u8) -> Trigger {
+		match (self.rising & 1 << pin != 0, self.falling & 1 << pin != 0) {
+			(true, false) => Trigger::Rising,
+			(false, true) => Trigger::Falling,
+			_ => Trigger::Both,
+		}
+	}
+
+	pub fn set(&mut self, pin: u8, trigger: Trigger) {
+		let (rising, falling) = match trigger {
+			Trigger::Rising => (true, false),
+			Trigger::Falling => (false, true),
+			Trigger::Both => (true, true),
+		};
+		self.rising = self.rising & !(1 << pin) | (rising as u16) << pin;
+		self.falling = self.falling & !(1 << pin) | (falling as u16) << pin;
+	}
+
+	/// Whether the pin levels are needed to filter the `pending` interrupts.
+	pub fn needs_levels(&self, pending: u16) -> bool {
+		pending & !(self.rising & self.falling) != 0
+	}
+
+	/// Removes the pending interrupts for edges which are not reported.
+	pub fn filter(&self, pending: u16, levels: u16) -> u16 {
+		pending & (levels & self.rising | !levels & self.falling)
+	}
+}
+
+/// A function called by [`Stmpe1600::dispatch`](crate::Stmpe1600::dispatch) for each interrupt on
+/// the pin it is registered for.
+pub type InterruptHandler = fn(InterruptEvent);
+
+/// The handler registered for each pin, shared by the blocking and asynchronous drivers.
+///
+/// The table is copied out before any handler is called, so handlers may register or remove
+/// handlers themselves.
+#[derive(Debug)]
+pub(crate) struct Handlers(Cell<[Option<InterruptHandler>; 16]>);
+
+impl Handlers {
+	pub fn new() -> Handlers {
+		Handlers(Cell::new([None; 16]))
+	}
+
+	/// Registers `handler` for `pin`, or removes the handler of `pin` if `handler` is `None`.
+	pub fn set<E>(&self, pin: u8, handler: Option<InterruptHandler>) -> Result<(), Error<E>> {
+		if pin >= 16 {
+			return Err(Error::InvalidPin);
+		}
+		let mut handlers = self.0.get();
+		handlers[pin as usize] = handler;
+		self.0.set(handlers);
+		Ok(())
+	}
+
+	/// The pins which have a handler.
+	pub fn registered(&self) -> PinSet {
+		let handlers = self.0.get();
+		(0..16)
+			.filter(|&pin| handlers[pin as usize].is_some())
+			.collect()
+	}
+
+	/// Calls the handler of the pin of each event, and returns the pins which had events.
+	pub fn call(&self, events: InterruptEvents) -> PinSet {
+		let handlers = self.0.get();
+		let fired = events.pending();
+		for event in events {
+			if let Some(handler) = handlers[event.pin as usize] {
+				handler(event);
+			}
+		}
+		fired
+	}
+}
+
+/// Interrupts which have been read from the STMPE1600, but not yet consumed.
+///
+/// Reading the interrupt status register clears it, so every pending bit which is read is kept
+/// here until it is handed out, along with the level of the pin when it was read.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Latch {
+	pending: u16,
+	levels: u16,
+}
+
+impl Latch {
+	/// Adds newly read interrupts, along with the pin levels read at the same time.
+	pub fn add(&mut self, pending: u16, levels: u16) {
+		self.levels = self.levels & !pending | levels & pending;
+		self.pending |= pending;
+	}
+
+	pub fn is_pending(&self, mask: u16) -> bool {
+		self.pending & mask != 0
+	}
+
+	pub fn clear(&mut self, mask: u16) {
+		self.pending &= !mask;
+	}
+
+	/// Hands out the latched interrupts of the pins in `mask`, and the levels of those pins,
+	/// leaving the interrupts of any other pins latched.
+	pub fn take(&mut self, mask: u16) -> (u16, u16) {
+		let pending = self.pending & mask;
+		self.pending &= !mask;
+		(pending, self.levels & pending)
+	}
+}
+
+/// A change on an interrupt pin, as reported by [`Stmpe1600::get_interrupt_events`](crate::Stmpe1600::get_interrupt_events).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterruptEvent {
+	/// The index of the pin which changed.
+	pub pin: u8,
+	/// The level of the pin when the interrupt was serviced.
+	pub level: PinState,
+	/// Whether the pin rose or fell.
+	pub edge: Edge,
+}
+
+/// An iterator over the [`InterruptEvent`]s read from the STMPE1600, in ascending pin order.
+///
+/// The events are decoded from a single read of the interrupt status register and a single read
+/// of the pin state register, so the events and levels are consistent with each other. Edges which
+/// are excluded by a pin's [`Trigger`] are not reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterruptEvents {
+	pending: PinSetIter,
+	levels: u16,
+}
+
+impl InterruptEvents {
+	pub(crate) fn new(pending: u16, levels: u16) -> InterruptEvents {
+		InterruptEvents {
+			pending: PinSet::from_bits(pending).iter(),
+			levels,
+		}
+	}
+
+	/// The pins whose interrupts have not been iterated over yet.
+	pub fn pending(&self) -> PinSet {
+		self.pending.remaining()
+	}
+
+	/// The pins which were high when the interrupt was serviced.
+	pub fn levels(&self) -> PinSet {
+		PinSet::from_bits(self.levels)
+	}
+}
+
+impl Iterator for InterruptEvents {
+	type Item = InterruptEvent;
+
+	fn next(&mut self) -> Option<InterruptEvent> {
+		let pin = self.pending.next()?;
+		let (level, edge) = if self.levels & 1 << pin != 0 {
+			(PinState::High, Edge::Rising)
+		} else {
+			(PinState::Low, Edge::Falling)
+		};
+		Some(InterruptEvent { pin, level, edge })
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.pending.size_hint()
+	}
+}
+
+impl ExactSizeIterator for InterruptEvents {}
+
+impl FusedIterator for InterruptEvents {}
@@ -8,19 +8,49 @@
 //! This works by using the I²C traits of `embedded-hal`, which allows for a specific HAL (hardware abstraction layer) to provide its own interface
 //! to allow using the specific implentation of I²C necessary to work on a specific device.
 //!
+//! # `embedded-hal` versions
+//! The driver is built on the `embedded-hal` 1.0 traits. HALs which only implement the `embedded-hal` 0.2 traits
+//! can still be used by enabling the `eh02` feature, which provides the
+#![cfg_attr(feature = "eh02", doc = "[`compat::I2cCompat`]")]
+#![cfg_attr(not(feature = "eh02"), doc = "`compat::I2cCompat`")]
+//! adapter for the I²C bus and implements the 0.2 `digital::v2` traits on [`Pin`] alongside the 1.0 ones.
+//!
+//! # Asynchronous driver
+//! With the `async` feature enabled, an asynchronous flavour of the driver built on `embedded-hal-async`
+//! is available in the
+#![cfg_attr(
+	feature = "async",
+	doc = "[`asynch`] module. It is constructed with [`Stmpe1600Builder::build_async`]."
+)]
+#![cfg_attr(
+	not(feature = "async"),
+	doc = "`asynch` module. It is constructed with `Stmpe1600Builder::build_async`."
+)]
+//!
 //! # Driver construction
 //! To construct the driver, you will need to use the [`Stmpe1600Builder`](struct.Stmpe1600Builder.html) struct.
 //! For more information on what configuration options can be changed, view the `Stmpe1600Builder` documentation.
 //! ```ignore
-//! let i2c = /* construct something implementing embedded_hal::blocking::i2c::{Read, Write} */;
+//! let i2c = /* construct something implementing embedded_hal::i2c::I2c */;
 //! let stmpe1600 = Stmpe1600Builder::new(i2c).build()?;
 //! ```
 //!
+//! If the driver cannot be built, `build` drops the I²C bus. To get the bus (and the host
+//! interrupt pin, if one was given) back along with the error, use
+//! [`try_build`](Stmpe1600Builder::try_build) instead:
+//! ```ignore
+//! let stmpe1600 = match Stmpe1600Builder::new(i2c).try_build() {
+//!     Ok(stmpe1600) => stmpe1600,
+//!     Err((error, i2c, _)) => { /* report the error, and carry on using the bus */ }
+//! };
+//! ```
+//!
 //! # Accessing I/O
 //! To access the I/O pins, call either [`Stmpe1600::pin_input`], [`Stmpe1600::pin_output`] or [`Stmpe1600::pin_interrupt`],
-//! which will return a [`Pin`](struct.Pin.html) object.
+//! which will return a [`Pin`](struct.Pin.html) object. To use several pins at the same time, call [`Stmpe1600::split`],
+//! which returns all 16 pins as independent handles in a [`Parts`] struct.
 //!
-//! This type implements [`embedded_hal::digital::v2::InputPin`] or [`embedded_hal::digital::v2::OutputPin`] (depending on the pin's mode),
+//! This type implements [`embedded_hal::digital::InputPin`] or [`embedded_hal::digital::OutputPin`] (depending on the pin's mode),
 //! which means that they can also be passed to any function which takes these types as arguments;
 //! this allows these pins to be passed transparently to platform-agnostic drivers easily and efficiently.
 //!
@@ -32,46 +62,77 @@
 //!
 //! let dev = I2cdev::new("/dev/i2c-1").unwrap();
 //! let stmpe1600 = Stmpe1600Builder::new(dev)
-//! 	.address(0x43)
-//! 	.build()
-//! 	.expect("Could not initialise STMPE1600 driver");
+//!     .address(0x43)
+//!     .build()
+//!     .expect("Could not initialise STMPE1600 driver");
 //! ```
 //!
 //! ## Read and write I/O pins
 //! ```rust,ignore
-//! use embedded_hal::digital::v2::{InputPin, OutputPin};
+//! use embedded_hal::digital::{InputPin, OutputPin};
 //! use linux_embedded_hal::I2cdev;
 //! use stmpe1600::Stmpe1600Builder;
 //!
 //! let dev = I2cdev::new("/dev/i2c-1").unwrap();
 //! let stmpe1600 = Stmpe1600Builder::new(dev)
-//! 	.build()
-//! 	.expect("Could not initialise STMPE1600 driver");
+//!     .build()
+//!     .expect("Could not initialise STMPE1600 driver");
 //!
-//! let mut input_pin = stmpe1600.pin_input(0);
-//! let mut output_pin = stmpe1600.pin_output(1);
+//! let pins = stmpe1600.split()?;
+//! let mut input_pin = pins.p0;
+//! let mut output_pin = pins.p1.into_output_pin()?;
 //!
 //! if input_pin.is_high()? {
-//! 	output_pin.set_high()?
+//!     output_pin.set_high()?
 //! } else {
-//! 	output_pin.set_low()?;
+//!     output_pin.set_low()?;
 //! }
 //! ```
 
 #![no_std]
 #![warn(missing_docs)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell, RefMut};
 use core::fmt::Debug;
-use embedded_hal::blocking::i2c::{Read, Write};
+use embedded_hal::digital;
+use embedded_hal::i2c::I2c;
 
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub mod asynch;
 mod builder;
-pub use builder::Stmpe1600Builder;
+#[cfg(feature = "eh02")]
+#[cfg_attr(docsrs, doc(cfg(feature = "eh02")))]
+pub mod compat;
+mod debounce;
 mod device;
-use device::{Register, Stmpe1600Device};
+mod gesture;
+mod interrupts;
+#[cfg(feature = "async")]
+mod notifier;
+mod pin_set;
 mod pins;
-use pins::modes;
-pub use pins::Pin;
+mod polling;
+mod queue;
+mod timestamp;
+
+use device::{Blocking, Register, Stmpe1600Device};
+use interrupts::Handlers;
+
+pub use builder::Stmpe1600Builder;
+pub use debounce::{ButtonAction, ButtonEvent, ButtonEvents, Debouncer};
+pub use gesture::{Gesture, GestureConfig, GestureDetector, GestureEvent, GestureEvents};
+pub use interrupts::{
+	Edge, InterruptEvent, InterruptEvents, InterruptHandler, InterruptInput, InterruptOutput,
+	NoInterruptPin, Trigger,
+};
+pub use pin_set::{PinSet, PinSetIter};
+pub use pins::{modes, Parts, Pin, PinIndex, PinNum};
+pub use pins::{P0, P1, P10, P11, P12, P13, P14, P15, P2, P3, P4, P5, P6, P7, P8, P9};
+pub use polling::ChangeDetector;
+pub use queue::{EventConsumer, EventProducer, EventQueue, InterruptReader};
+pub use timestamp::{Clock, TimestampedEvent, TimestampedEvents};
 
 /// The default I²C address for the STMPE1600.
 pub const DEFAULT_ADDRESS: u8 = 0x42;
@@ -85,6 +146,41 @@ enum PinMode {
 	Interrupt,
 }
 
+/// Gets the set of pins which are configured in `mode`.
+fn pins_in_mode(pins: &[PinMode; 16], mode: PinMode) -> PinSet {
+	pins.iter()
+		.enumerate()
+		.filter(|(_, m)| **m == mode)
+		.map(|(i, _)| i as u8)
+		.collect()
+}
+
+/// Works out the pin modes resulting from writing `gpdr` to the direction register.
+///
+/// Returns the new modes, along with a mask of the interrupt pins which become outputs and so need
+/// their interrupts disabled.
+fn modes_for_direction(pins: &[PinMode; 16], gpdr: u16) -> ([PinMode; 16], u16) {
+	let mut modes = *pins;
+	let mut disable = 0;
+	for (i, mode) in modes.iter_mut().enumerate() {
+		match (*mode, gpdr & 1 << i != 0) {
+			(PinMode::Interrupt, true) => {
+				disable |= 1 << i;
+				*mode = PinMode::Output;
+			}
+			(_, true) => *mode = PinMode::Output,
+			(PinMode::Output, false) => *mode = PinMode::Input,
+			_ => {}
+		}
+	}
+	(modes, disable)
+}
+
+/// Whether every pin is in input mode, as is needed to split the driver.
+fn all_inputs(pins: &[PinMode; 16]) -> bool {
+	pins.iter().all(|&mode| mode == PinMode::Input)
+}
+
 /// Input/Interrupt polarity.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -100,72 +196,651 @@ pub enum Error<E> {
 	I2CError(E),
 	/// Invalid device ID
 	InvalidDeviceID,
+	/// Pin index out of range (the STMPE1600 has pins 0 to 15)
+	InvalidPin,
+	/// The driver is already in use, for example if it is accessed again from inside a handler
+	/// which is running while the driver is busy
+	Busy,
+	/// The host pin connected to the interrupt output could not be read
+	InterruptPin,
+	/// The interrupt output is disabled, so there is no interrupt to wait for
+	InterruptOutputDisabled,
+	/// The pin's interrupt is masked, so there is no interrupt to wait for
+	PinMasked,
+	/// The pin's [`Trigger`] filters out the edge being waited for, so it is never reported
+	EdgeFiltered,
+	/// A pin is not in input mode, so the driver cannot be split into input pins
+	PinNotInput,
+	/// The driver has already been split into its pins
+	AlreadySplit,
+}
+
+impl<E: Debug> digital::Error for Error<E> {
+	fn kind(&self) -> digital::ErrorKind {
+		digital::ErrorKind::Other
+	}
 }
 
 /// A struct representing the STMPE1600 device driver.
+///
+/// `INT` is the host pin connected to the STMPE1600's interrupt output, if one was given to
+/// [`Stmpe1600Builder::interrupt_pin`].
 #[derive(Debug)]
-pub struct Stmpe1600<I2C> {
+pub struct Stmpe1600<I2C, INT = NoInterruptPin> {
 	device: RefCell<Stmpe1600Device<I2C>>,
-	pins: RefCell<[PinMode; 16]>,
+	handlers: Handlers,
+	int: RefCell<INT>,
+	split: Cell<bool>,
 }
 
-impl<I2C, E> Stmpe1600<I2C>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
-	/// Create a [`Pin`] which corresponds to the specified pin, configured in input mode.
-	///
-	/// If the specified pin is not already configured in input mode, the mode will be changed
-	/// automatically.
-	///
-	/// This function will panic if `pin > 16`.
-	pub fn pin_input(&mut self, pin: u8) -> Result<Pin<'_, I2C, modes::Input>, Error<E>> {
-		assert!(pin < 16);
-		let mode = self.pins.borrow()[pin as usize];
-		match mode {
-			PinMode::Input => Ok(Pin::new(self, pin)),
-			PinMode::Output => Pin::<I2C, modes::Output>::new(self, pin).into_input_pin(),
-			PinMode::Interrupt => Pin::<I2C, modes::Interrupt>::new(self, pin).into_input_pin(),
-		}
-	}
+/// The borrowed device, as handed out by [`Stmpe1600::device`].
+pub(crate) type DeviceGuard<'a, I2C> = RefMut<'a, Stmpe1600Device<I2C>>;
 
-	/// Create a [`Pin`] which corresponds to the specified pin, configured in output mode.
-	///
-	/// If the specified pin is not already configured in output mode, the mode will be changed
-	/// automatically.
-	///
-	/// This function will panic if `pin > 16`.
-	pub fn pin_output(&mut self, pin: u8) -> Result<Pin<'_, I2C, modes::Output>, Error<E>> {
-		assert!(pin < 16);
-		let mode = self.pins.borrow()[pin as usize];
-		match mode {
-			PinMode::Input => Pin::<I2C, modes::Input>::new(self, pin).into_output_pin(),
-			PinMode::Output => Ok(Pin::new(self, pin)),
-			PinMode::Interrupt => Pin::<I2C, modes::Interrupt>::new(self, pin).into_output_pin(),
+impl<I2C, INT> Stmpe1600<I2C, INT> {
+	pub(crate) fn new(device: Stmpe1600Device<I2C, Blocking>, int: INT) -> Stmpe1600<I2C, INT> {
+		Stmpe1600 {
+			device: RefCell::new(device),
+			handlers: Handlers::new(),
+			int: RefCell::new(int),
+			split: Cell::new(false),
 		}
 	}
 
-	/// Create a [`Pin`] which corresponds to the specified pin, configured in interrupt mode.
-	///
-	/// If the specified pin is not already configured in interrupt mode, the mode will be changed
-	/// automatically.
-	///
-	/// This function will panic if `pin > 16`.
-	pub fn pin_interrupt(&mut self, pin: u8) -> Result<Pin<'_, I2C, modes::Interrupt>, Error<E>> {
-		assert!(pin < 16);
-		let mode = self.pins.borrow()[pin as usize];
-		match mode {
-			PinMode::Input => Pin::<I2C, modes::Input>::new(self, pin).into_interrupt_pin(),
-			PinMode::Output => Pin::<I2C, modes::Output>::new(self, pin).into_interrupt_pin(),
-			PinMode::Interrupt => Ok(Pin::new(self, pin)),
-		}
+	/// Borrows the device, failing with [`Error::Busy`] rather than panicking if it is in use.
+	pub(crate) fn device<E>(&self) -> Result<DeviceGuard<'_, I2C>, Error<E>> {
+		self.device.try_borrow_mut().map_err(|_| Error::Busy)
 	}
 
-	/// Gets the pending interrupts and returns them in an array.
-	///
-	/// This function clears any pending bits from the STMPE1600,
-	/// and in doing so, stops triggering the interrupt output pin.
-	pub fn get_interrupts(&self) -> Result<[bool; 16], Error<E>> {
-		self.device.borrow_mut().get_interrupts()
+	/// Called once the interrupt status has been read into the latch. Nothing waits for this in
+	/// the blocking driver.
+	fn interrupts_read(&self) {}
+}
+
+impl<I2C, INT> Stmpe1600<I2C, INT>
+where
+	INT: InterruptInput,
+{
+	/// Reads the host pin to find out whether the interrupt output, which is driven to `polarity`
+	/// while an interrupt is pending, is asserted.
+	fn read_interrupt_pin<E>(&self, polarity: Polarity) -> Result<bool, Error<E>> {
+		let mut int = self.int.try_borrow_mut().map_err(|_| Error::Busy)?;
+		interrupts::is_asserted(&mut *int, polarity)
 	}
 }
+
+/// Implements the functions of the driver, for one way of accessing the bus.
+///
+/// The blocking driver and the async driver are both generated from this one definition, as the
+/// bus operations of `Stmpe1600Device` are by `impl_bus!`, so they only differ in whether the
+/// functions are `async`, and whether the calls which talk to the STMPE1600 are awaited. It is
+/// expanded in the module which defines the driver, where `Stmpe1600`, `Pin`, `Parts`,
+/// `DeviceGuard` and `I2c` name that module's flavour of each. The driver itself provides
+/// `device`, `read_interrupt_pin` and `interrupts_read`, which are all that differ between them.
+/// The last argument is the body of the `compile_fail` example for the `pin_*_const` functions,
+/// which has to build and use the driver in that module's flavour to fail for the right reason.
+macro_rules! impl_driver {
+	([$($async:tt)?], [$($await:tt)*], $const_pin_example:literal) => {
+		impl<I2C, INT> Stmpe1600<I2C, INT> {
+			/// Consumes the driver, and returns the I²C bus without changing the STMPE1600's
+			/// configuration.
+			///
+			/// See [`destroy`](Stmpe1600::destroy) to also return the pins to a safe state. The
+			/// host interrupt pin, if there is one, is dropped; use
+			/// [`release_all`](Stmpe1600::release_all) to get it back as well.
+			pub fn release(self) -> I2C {
+				self.release_all().0
+			}
+
+			/// Consumes the driver, and returns the I²C bus and the host interrupt pin given to
+			/// [`Stmpe1600Builder::interrupt_pin`](crate::Stmpe1600Builder::interrupt_pin) (or
+			/// [`NoInterruptPin`](crate::NoInterruptPin)) without changing the STMPE1600's
+			/// configuration.
+			pub fn release_all(self) -> (I2C, INT) {
+				(self.device.into_inner().release(), self.int.into_inner())
+			}
+		}
+
+		impl<I2C, E, INT> Stmpe1600<I2C, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+		{
+			/// Create a [`Pin`] which corresponds to the specified pin, configured in input mode.
+			///
+			/// If the specified pin is not already configured in input mode, the mode will be
+			/// changed automatically.
+			///
+			/// This function will return [`Error::InvalidPin`](crate::Error::InvalidPin) if
+			/// `pin > 15`. Use [`pin_input_const`](Stmpe1600::pin_input_const) to check the pin
+			/// index at compile time instead.
+			pub $($async)? fn pin_input(
+				&mut self,
+				pin: u8,
+			) -> Result<Pin<'_, I2C, modes::Input, u8, INT>, Error<E>> {
+				self.pin(pin, PinMode::Input)$($await)*
+			}
+
+			/// Create a [`Pin`] which corresponds to the specified pin, configured in output mode.
+			///
+			/// If the specified pin is not already configured in output mode, the mode will be
+			/// changed automatically.
+			///
+			/// This function will return [`Error::InvalidPin`](crate::Error::InvalidPin) if
+			/// `pin > 15`. Use [`pin_output_const`](Stmpe1600::pin_output_const) to check the pin
+			/// index at compile time instead.
+			pub $($async)? fn pin_output(
+				&mut self,
+				pin: u8,
+			) -> Result<Pin<'_, I2C, modes::Output, u8, INT>, Error<E>> {
+				self.pin(pin, PinMode::Output)$($await)*
+			}
+
+			/// Create a [`Pin`] which corresponds to the specified pin, configured in interrupt
+			/// mode.
+			///
+			/// If the specified pin is not already configured in interrupt mode, the mode will be
+			/// changed automatically.
+			///
+			/// This function will return [`Error::InvalidPin`](crate::Error::InvalidPin) if
+			/// `pin > 15`. Use [`pin_interrupt_const`](Stmpe1600::pin_interrupt_const) to check
+			/// the pin index at compile time instead.
+			pub $($async)? fn pin_interrupt(
+				&mut self,
+				pin: u8,
+			) -> Result<Pin<'_, I2C, modes::Interrupt, u8, INT>, Error<E>> {
+				self.pin(pin, PinMode::Interrupt)$($await)*
+			}
+
+			/// Create a [`Pin`] for pin `N`, configured in input mode.
+			///
+			/// This behaves like [`pin_input`](Stmpe1600::pin_input), but the pin index is checked
+			/// at compile time, so `N > 15` fails to build rather than returning an error.
+			///
+			/// The check is only made when the code is built, as it is evaluated once `N` is known
+			/// for each use of the function. `cargo check` does not get that far, and accepts an
+			/// out of range index; `cargo build` rejects it.
+			///
+			/// ```rust,compile_fail
+			/// use embedded_hal_mock::eh1::i2c::Mock as I2cMock;
+			/// use stmpe1600::Stmpe1600Builder;
+			///
+			#[doc = $const_pin_example]
+			/// ```
+			pub $($async)? fn pin_input_const<const N: u8>(
+				&mut self,
+			) -> Result<Pin<'_, I2C, modes::Input, PinNum<N>, INT>, Error<E>> {
+				self.pin(PinNum, PinMode::Input)$($await)*
+			}
+
+			/// Create a [`Pin`] for pin `N`, configured in output mode.
+			///
+			/// This behaves like [`pin_output`](Stmpe1600::pin_output), but the pin index is
+			/// checked at compile time, so `N > 15` fails to build rather than returning an error.
+			/// As with [`pin_input_const`](Stmpe1600::pin_input_const), `cargo check` does not
+			/// catch this.
+			pub $($async)? fn pin_output_const<const N: u8>(
+				&mut self,
+			) -> Result<Pin<'_, I2C, modes::Output, PinNum<N>, INT>, Error<E>> {
+				self.pin(PinNum, PinMode::Output)$($await)*
+			}
+
+			/// Create a [`Pin`] for pin `N`, configured in interrupt mode.
+			///
+			/// This behaves like [`pin_interrupt`](Stmpe1600::pin_interrupt), but the pin index is
+			/// checked at compile time, so `N > 15` fails to build rather than returning an error.
+			/// As with [`pin_input_const`](Stmpe1600::pin_input_const), `cargo check` does not
+			/// catch this.
+			pub $($async)? fn pin_interrupt_const<const N: u8>(
+				&mut self,
+			) -> Result<Pin<'_, I2C, modes::Interrupt, PinNum<N>, INT>, Error<E>> {
+				self.pin(PinNum, PinMode::Interrupt)$($await)*
+			}
+
+			$($async)? fn pin<MODE, P: PinIndex>(
+				&self,
+				pin: P,
+				mode: PinMode,
+			) -> Result<Pin<'_, I2C, MODE, P, INT>, Error<E>> {
+				if pin.index() >= 16 {
+					return Err(Error::InvalidPin);
+				}
+				self.set_mode(pin.index(), mode)$($await)*?;
+				Ok(Pin::new(self, pin))
+			}
+
+			/// Resets the STMPE1600, then consumes the driver and returns the I²C bus.
+			///
+			/// The software reset returns every pin to input mode, and disables all interrupts and
+			/// the interrupt output pin, so nothing is driven by the STMPE1600 once the bus has
+			/// been released.
+			///
+			/// If the reset fails, the I²C bus is still returned, along with the error.
+			pub $($async)? fn destroy(self) -> Result<I2C, (Error<E>, I2C)> {
+				self.destroy_all()
+					$($await)*
+					.map(|(i2c, _)| i2c)
+					.map_err(|(error, i2c, _)| (error, i2c))
+			}
+
+			/// Resets the STMPE1600, then consumes the driver and returns the I²C bus and the host
+			/// interrupt pin, as [`release_all`](Stmpe1600::release_all) does.
+			///
+			/// If the reset fails, the I²C bus and host interrupt pin are still returned, along
+			/// with the error.
+			pub $($async)? fn destroy_all(mut self) -> Result<(I2C, INT), (Error<E>, I2C, INT)> {
+				let result = self.device.get_mut().reset()$($await)*;
+				let (i2c, int) = self.release_all();
+				match result {
+					Ok(()) => Ok((i2c, int)),
+					Err(error) => Err((error, i2c, int)),
+				}
+			}
+
+			/// Splits the driver into its 16 pins, so that each can be owned and used
+			/// independently.
+			///
+			/// The pins only borrow the driver, so its own functions, such as
+			/// [`get_interrupt_events`](Stmpe1600::get_interrupt_events) or
+			/// [`set_outputs`](Stmpe1600::set_outputs), can still be used alongside them. The
+			/// driver can only be split once; any later call returns
+			/// [`Error::AlreadySplit`](crate::Error::AlreadySplit).
+			///
+			/// All pins are returned in input mode, and nothing is written to the STMPE1600, so
+			/// every pin must still be an input. If any pin has been configured otherwise,
+			/// including by the builder's
+			/// [`pin_output`](crate::Stmpe1600Builder::pin_output) or
+			/// [`pin_interrupt`](crate::Stmpe1600Builder::pin_interrupt), this function will
+			/// return [`Error::PinNotInput`](crate::Error::PinNotInput), and can be called again
+			/// once those pins are back in input mode. Use the `into_output_pin` and
+			/// `into_interrupt_pin` functions to change the modes of the pins once they are split.
+			/// The driver cannot be split again to get back a pin which is lost, so where a mode
+			/// change may fail, use `try_into_output_pin` and `try_into_interrupt_pin` instead,
+			/// which hand the pin back along with the error.
+			///
+			/// Output levels and polarity inversions set by the builder are kept, so an output can
+			/// still be set up without glitching, by giving the builder its level with
+			/// [`output_level`](crate::Stmpe1600Builder::output_level):
+			///
+			/// ```rust,ignore
+			/// let stmpe1600 = Stmpe1600Builder::new(i2c)
+			///     .output_level(3, PinState::High)
+			///     .build()?;
+			/// let pins = stmpe1600.split()?;
+			/// // Pin 3 starts driving high as soon as it becomes an output.
+			/// let p3 = pins.p3.into_output_pin()?;
+			/// ```
+			pub $($async)? fn split(&self) -> Result<Parts<'_, I2C, INT>, Error<E>> {
+				// Claim the pins before waiting for the device, so that a concurrent call cannot
+				// also get them; the claim is given up again if the pins cannot be handed out.
+				if self.split.replace(true) {
+					return Err(Error::AlreadySplit);
+				}
+				let inputs = match self.device()$($await)* {
+					Ok(device) => all_inputs(device.modes()),
+					Err(error) => {
+						self.split.set(false);
+						return Err(error);
+					}
+				};
+				if !inputs {
+					self.split.set(false);
+					return Err(Error::PinNotInput);
+				}
+				Ok(Parts::new(self))
+			}
+
+			/// Gets the pending interrupts, as the set of pins which have an interrupt pending.
+			///
+			/// This function clears any pending bits from the STMPE1600,
+			/// and in doing so, stops triggering the interrupt output pin.
+			///
+			/// Pins with a [`Trigger`](crate::Trigger) other than
+			/// [`Trigger::Both`](crate::Trigger::Both) are only reported for the edges they are
+			/// interested in, which needs the pin states to be read as well.
+			///
+			/// Interrupts which were read earlier by [`Pin::is_pending`] and not yet cleared are
+			/// included too, and all of the returned interrupts are consumed. To share the
+			/// interrupts between several parts of an application, use [`Pin::is_pending`] and
+			/// [`Pin::clear_pending`] instead, which only consume the interrupt of a single pin.
+			pub $($async)? fn get_interrupts(&self) -> Result<PinSet, Error<E>> {
+				Ok(self.latch_interrupts(false)$($await)*?.take_interrupts())
+			}
+
+			/// Gets the pending interrupts, along with the level each pin changed to.
+			///
+			/// The interrupt status register is read, then the pin state register, and the two are
+			/// combined into one [`InterruptEvent`](crate::InterruptEvent) per pending pin, which
+			/// records whether the pin rose or fell. If nothing is pending, the pin state register
+			/// is not read. As with [`get_interrupts`](Stmpe1600::get_interrupts), reading the
+			/// status clears the pending bits, and any interrupts latched by [`Pin::is_pending`]
+			/// are included and consumed.
+			///
+			/// ```rust,ignore
+			/// use stmpe1600::Edge;
+			///
+			/// for event in stmpe1600.get_interrupt_events()? {
+			///     if event.edge == Edge::Falling {
+			///         // Button on `event.pin` was pressed.
+			///     }
+			/// }
+			/// ```
+			pub $($async)? fn get_interrupt_events(&self) -> Result<InterruptEvents, Error<E>> {
+				Ok(self
+					.latch_interrupts(true)
+					$($await)*?
+					.take_interrupt_events(PinSet::all()))
+			}
+
+			/// Gets the pending interrupts as events, as
+			/// [`get_interrupt_events`](Stmpe1600::get_interrupt_events) does, stamped with the
+			/// time they were read.
+			///
+			/// `clock` is read once, just before the interrupt status is read, which when called
+			/// from the host's interrupt handler is as close as possible to when the edges
+			/// happened. Interrupts which were latched by an earlier read (for example by
+			/// [`Pin::is_pending`]) get the same timestamp as the rest, so are stamped later than
+			/// they were read.
+			pub $($async)? fn get_timestamped_events<C: Clock>(
+				&self,
+				clock: &mut C,
+			) -> Result<TimestampedEvents, Error<E>> {
+				let timestamp = clock.now();
+				let events = self.get_interrupt_events()$($await)*?;
+				Ok(TimestampedEvents::new(events, timestamp))
+			}
+
+			/// Reads the pending interrupts into `debouncer`, stamped by `clock`, then reports the
+			/// buttons which have settled into a new state.
+			///
+			/// This should be called whenever the interrupt output is asserted, and again once
+			/// [`Debouncer::next_deadline`](crate::Debouncer::next_deadline) has passed, so that
+			/// pins which stop bouncing are reported. See [`Debouncer`](crate::Debouncer).
+			pub $($async)? fn poll_debounced<C: Clock>(
+				&self,
+				debouncer: &mut Debouncer,
+				clock: &mut C,
+			) -> Result<ButtonEvents, Error<E>> {
+				let events = self.get_timestamped_events(clock)$($await)*?;
+				Ok(debouncer.settle(events, clock.now()))
+			}
+
+			/// Reads the pin states, and reports the pins watched by `detector` which changed since
+			/// it last polled, as if they had raised interrupts.
+			///
+			/// The events are filtered by each pin's [`Trigger`](crate::Trigger), exactly as those
+			/// returned by [`get_interrupt_events`](Stmpe1600::get_interrupt_events) are. See
+			/// [`ChangeDetector`](crate::ChangeDetector).
+			pub $($async)? fn poll_changes(
+				&self,
+				detector: &mut ChangeDetector,
+			) -> Result<InterruptEvents, Error<E>> {
+				self.device()$($await)*?.poll_changes(detector)$($await)*
+			}
+
+			/// Gets the set of interrupt pins whose interrupts are masked.
+			pub $($async)? fn interrupt_mask(&self) -> Result<PinSet, Error<E>> {
+				self.device()$($await)*?.interrupt_mask()$($await)*
+			}
+
+			/// Masks the interrupts of the interrupt pins in `masked`, and unmasks those of all
+			/// other interrupt pins, in a single write.
+			///
+			/// Masked pins stay in interrupt mode, so existing [`Pin`] handles are unaffected, but
+			/// they no longer raise interrupts until they are unmasked. Pins in `masked` which are
+			/// not interrupt pins are ignored.
+			///
+			/// ```rust,ignore
+			/// // Silence everything while the motor starts, then restore all interrupts.
+			/// stmpe1600.set_interrupt_mask(PinSet::all())?;
+			/// start_motor();
+			/// stmpe1600.set_interrupt_mask(PinSet::empty())?;
+			/// ```
+			pub $($async)? fn set_interrupt_mask(&self, masked: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?.set_interrupt_mask(masked)$($await)*
+			}
+
+			/// Reads back the configuration of the interrupt output pin from the STMPE1600.
+			pub $($async)? fn interrupt_output(&self) -> Result<InterruptOutput, Error<E>> {
+				self.device()$($await)*?.interrupt_output()$($await)*
+			}
+
+			/// Enables the interrupt output pin, which is driven to `polarity` while an interrupt
+			/// is pending.
+			pub $($async)? fn enable_interrupt_output(&self, polarity: Polarity) -> Result<(), Error<E>> {
+				self.device()$($await)*?
+					.modify_interrupt_output(|_| InterruptOutput {
+						enabled: true,
+						polarity,
+					})
+					$($await)*
+			}
+
+			/// Disables the interrupt output pin.
+			///
+			/// Interrupts are still latched in the interrupt status register while the output is
+			/// disabled, so they can be polled with [`get_interrupts`](Stmpe1600::get_interrupts).
+			pub $($async)? fn disable_interrupt_output(&self) -> Result<(), Error<E>> {
+				self.device()$($await)*?
+					.modify_interrupt_output(|output| InterruptOutput {
+						enabled: false,
+						..output
+					})
+					$($await)*
+			}
+
+			/// Sets the polarity of the interrupt output pin, without enabling or disabling it.
+			pub $($async)? fn set_interrupt_polarity(&self, polarity: Polarity) -> Result<(), Error<E>> {
+				self.device()$($await)*?
+					.modify_interrupt_output(|output| InterruptOutput { polarity, ..output })
+					$($await)*
+			}
+
+			/// Registers a handler to be called by [`dispatch`](Stmpe1600::dispatch) for each
+			/// interrupt on `pin`, replacing any handler which was registered before.
+			///
+			/// This function will return [`Error::InvalidPin`](crate::Error::InvalidPin) if
+			/// `pin > 15`.
+			pub fn set_handler(&self, pin: u8, handler: InterruptHandler) -> Result<(), Error<E>> {
+				self.handlers.set(pin, Some(handler))
+			}
+
+			/// Removes the handler registered for `pin`, if there is one.
+			///
+			/// This function will return [`Error::InvalidPin`](crate::Error::InvalidPin) if
+			/// `pin > 15`.
+			pub fn remove_handler(&self, pin: u8) -> Result<(), Error<E>> {
+				self.handlers.set(pin, None)
+			}
+
+			/// Reads the pending interrupts, and calls the registered handler of each pin which
+			/// fired.
+			///
+			/// This is intended to be called from the handler of the host's interrupt line
+			/// connected to the STMPE1600's interrupt output. Only the interrupts of pins with a
+			/// handler are consumed; those of other pins stay latched for [`Pin::is_pending`]. The
+			/// driver is no longer borrowed when the handlers are called, so they are free to use
+			/// it (for example through a `static`).
+			///
+			/// Returns the set of pins whose handlers were called.
+			///
+			/// ```rust,ignore
+			/// use stmpe1600::InterruptEvent;
+			///
+			/// fn on_button(event: InterruptEvent) {
+			///     // ...
+			/// }
+			///
+			/// stmpe1600.set_handler(0, on_button)?;
+			///
+			/// // In the host's interrupt handler:
+			/// stmpe1600.dispatch()?;
+			/// ```
+			pub $($async)? fn dispatch(&self) -> Result<PinSet, Error<E>> {
+				let registered = self.handlers.registered();
+				let events = self
+					.latch_interrupts(true)
+					$($await)*?
+					.take_interrupt_events(registered);
+				Ok(self.handlers.call(events))
+			}
+
+			/// Reads the pending interrupts, and adds an [`InterruptEvent`](crate::InterruptEvent)
+			/// for each of them to the queue which `producer` fills.
+			///
+			/// Every pending interrupt is consumed; those which do not fit in the queue are
+			/// dropped, and counted by [`EventConsumer::overflows`](crate::EventConsumer::overflows).
+			/// The driver cannot be shared with an interrupt handler, so this is for when the
+			/// driver itself reads the interrupts, for example in a task which feeds other parts of
+			/// the application. To fill the queue from the host's interrupt handler while the
+			/// driver is used from the main loop, use an [`InterruptReader`](crate::InterruptReader).
+			///
+			/// Returns the set of pins whose events were queued. See
+			/// [`EventQueue`](crate::EventQueue).
+			pub $($async)? fn queue_interrupt_events<const N: usize>(
+				&self,
+				producer: &mut EventProducer<'_, N>,
+			) -> Result<PinSet, Error<E>> {
+				Ok(producer.push_events(self.get_interrupt_events()$($await)*?))
+			}
+
+			/// Is the STMPE1600's interrupt output asserted, according to the host pin given to
+			/// [`Stmpe1600Builder::interrupt_pin`](crate::Stmpe1600Builder::interrupt_pin)?
+			///
+			/// This only reads the host pin, and does not talk to the STMPE1600, so it is cheap
+			/// enough to call on every iteration of a main loop. The pin is compared against the
+			/// polarity the interrupt output was last configured with, by
+			/// [`Stmpe1600Builder::interrupts`](crate::Stmpe1600Builder::interrupts) or
+			/// [`enable_interrupt_output`](Stmpe1600::enable_interrupt_output).
+			///
+			/// If there is no host pin, or the interrupt output is disabled, there is no way of
+			/// knowing whether an interrupt is pending, so this always returns `true`.
+			///
+			/// While there is a host pin and the interrupt output is enabled,
+			/// [`get_interrupts`](Stmpe1600::get_interrupts),
+			/// [`get_interrupt_events`](Stmpe1600::get_interrupt_events),
+			/// [`dispatch`](Stmpe1600::dispatch) and [`Pin::is_pending`] use this to skip reading
+			/// the interrupt status register when nothing is pending.
+			///
+			/// ```rust,ignore
+			/// let stmpe1600 = Stmpe1600Builder::new(i2c)
+			///     .pin_interrupt(0)
+			///     .interrupts(Polarity::Low)
+			///     .interrupt_pin(int_pin)
+			///     .build()?;
+			///
+			/// loop {
+			///     if stmpe1600.interrupt_asserted()? {
+			///         stmpe1600.dispatch()?;
+			///     }
+			///     // ...
+			/// }
+			/// ```
+			pub $($async)? fn interrupt_asserted(&self) -> Result<bool, Error<E>> {
+				let polarity = match self.device()$($await)*?.interrupt_polarity() {
+					Some(polarity) => polarity,
+					None => return Ok(true),
+				};
+				self.read_interrupt_pin(polarity)
+			}
+
+			/// Reads the pending interrupts into the latch, unless the host pin shows that none are
+			/// pending, and returns the device to take them from.
+			pub(crate) $($async)? fn latch_interrupts(
+				&self,
+				levels: bool,
+			) -> Result<DeviceGuard<'_, I2C>, Error<E>> {
+				let read = self.interrupt_asserted()$($await)*?;
+				let mut dev = self.device()$($await)*?;
+				if read {
+					dev.read_interrupts(levels)$($await)*?;
+					self.interrupts_read();
+				}
+				Ok(dev)
+			}
+
+			/// Reads the current state of all 16 pins at once, returning the set of pins which are
+			/// high.
+			pub $($async)? fn read_port(&self) -> Result<PinSet, Error<E>> {
+				self.device()$($await)*?
+					.read_reg(Register::GPMR)
+					$($await)*
+					.map(PinSet::from_bits)
+			}
+
+			/// Sets the output state of all 16 pins at once in a single write, driving the pins in
+			/// `high` high and all others low.
+			///
+			/// Levels for pins which are not configured as outputs are latched by the STMPE1600,
+			/// and take effect if the pin is later made an output.
+			pub $($async)? fn write_port(&self, high: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?.write_reg(Register::GPSR, high.bits())$($await)*
+			}
+
+			/// Drives the pins in `set` high and the pins in `clear` low, in a single write.
+			///
+			/// All affected pins change at the same time. Pins in neither set keep their current
+			/// state, and pins in both sets are driven high. With the register cache disabled, the
+			/// output register is read back first, but is still updated with one write.
+			pub $($async)? fn set_outputs(&self, set: PinSet, clear: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?.set_outputs(set, clear)$($await)*
+			}
+
+			/// Toggles the output state of the pins in `pins`, in a single write.
+			///
+			/// All affected pins change at the same time. With the register cache disabled, the
+			/// output register is read back first, but is still updated with one write.
+			pub $($async)? fn toggle_outputs(&self, pins: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?.toggle_outputs(pins)$($await)*
+			}
+
+			/// Gets the direction of all 16 pins, as the set of pins which are outputs.
+			pub $($async)? fn direction(&self) -> Result<PinSet, Error<E>> {
+				self.device()$($await)*?
+					.read_reg_cached(Register::GPDR)
+					$($await)*
+					.map(PinSet::from_bits)
+			}
+
+			/// Sets the direction of all 16 pins, making the pins in `outputs` outputs and all
+			/// others inputs.
+			///
+			/// Interrupt pins which become outputs have their interrupts disabled before the
+			/// direction changes, and any interrupt latched for them is dropped. Any existing
+			/// [`Pin`] handles are not updated, so this should be used in place of, rather than
+			/// alongside, the per-pin mode changes.
+			pub $($async)? fn set_direction(&self, outputs: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?.set_direction(outputs)$($await)*
+			}
+
+			/// Gets the polarity inversion of all 16 pins, as the set of pins which are inverted.
+			pub $($async)? fn polarity_inversion(&self) -> Result<PinSet, Error<E>> {
+				self.device()$($await)*?
+					.read_reg_cached(Register::GPPIR)
+					$($await)*
+					.map(PinSet::from_bits)
+			}
+
+			/// Sets the polarity inversion of all 16 pins, inverting the pins in `inverted` and no
+			/// others.
+			pub $($async)? fn set_polarity_inversion(&self, inverted: PinSet) -> Result<(), Error<E>> {
+				self.device()$($await)*?
+					.write_reg(Register::GPPIR, inverted.bits())
+					$($await)*
+			}
+
+			/// Changes the mode of a pin, only touching the registers which need to change.
+			pub(crate) $($async)? fn set_mode(&self, pin: u8, mode: PinMode) -> Result<(), Error<E>> {
+				self.device()$($await)*?.set_mode(pin, mode)$($await)*
+			}
+		}
+	};
+}
+#[cfg(feature = "async")]
+pub(crate) use impl_driver;
+
+impl_driver!(
+	[],
+	[],
+	"let mut stmpe1600 = Stmpe1600Builder::new(I2cMock::new(&[])).build().unwrap();
+let pin = stmpe1600.pin_input_const::<16>();"
+);
@@ -0,0 +1,58 @@
+//! Wakes the tasks waiting on the interrupt output, once the interrupt status has been read.
+
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::future::poll_fn;
+use core::task::Poll;
+use embassy_sync::waitqueue::MultiWakerRegistration;
+
+/// Wakes every task waiting for new interrupts to be latched.
+///
+/// Only one task at a time can wait on the host's interrupt pin, so the others wait here instead,
+/// and are woken each time the interrupt status is read to check whether any of the newly latched
+/// interrupts are theirs. Each read bumps a generation count, so that a task which checks the
+/// latch and then waits cannot miss a read which happened in between.
+pub(crate) struct Notifier {
+	generation: Cell<u32>,
+	wakers: RefCell<MultiWakerRegistration<16>>,
+}
+
+impl Notifier {
+	pub const fn new() -> Notifier {
+		Notifier {
+			generation: Cell::new(0),
+			wakers: RefCell::new(MultiWakerRegistration::new()),
+		}
+	}
+
+	/// The current generation, to be passed to [`wait`](Notifier::wait).
+	pub fn generation(&self) -> u32 {
+		self.generation.get()
+	}
+
+	/// Records that the interrupt status has been read, and wakes every waiting task.
+	pub fn notify(&self) {
+		self.generation.set(self.generation.get().wrapping_add(1));
+		self.wakers.borrow_mut().wake();
+	}
+
+	/// Waits until the generation has moved on from `seen`.
+	pub async fn wait(&self, seen: u32) {
+		poll_fn(|cx| {
+			if self.generation.get() != seen {
+				return Poll::Ready(());
+			}
+			self.wakers.borrow_mut().register(cx.waker());
+			Poll::Pending
+		})
+		.await
+	}
+}
+
+impl fmt::Debug for Notifier {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Notifier")
+			.field("generation", &self.generation.get())
+			.finish_non_exhaustive()
+	}
+}
@@ -0,0 +1,201 @@
+//! A set of pins, stored as a bitmask.
+
+use core::fmt;
+use core::iter::{FromIterator, FusedIterator};
+use core::ops::{
+	BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+};
+
+/// A set of the STMPE1600's pins, where bit `n` of the underlying [`u16`] is pin `n`.
+///
+/// This is the type used wherever the driver reads or writes several pins at once, such as the
+/// pending interrupts, the port state, and the direction and polarity inversion masks.
+///
+/// ```
+/// use stmpe1600::PinSet;
+///
+/// let buttons: PinSet = [0, 1, 2].iter().copied().collect();
+/// let pending = PinSet::from_bits(0b0110);
+///
+/// assert!((buttons & pending).contains(2));
+/// assert_eq!((buttons - pending).iter().collect::<Vec<_>>(), [0]);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PinSet(u16);
+
+impl PinSet {
+	/// The set containing no pins.
+	pub const fn empty() -> PinSet {
+		PinSet(0)
+	}
+
+	/// The set containing all 16 pins.
+	pub const fn all() -> PinSet {
+		PinSet(0xFFFF)
+	}
+
+	/// Creates a set from a bitmask, where bit `n` is pin `n`.
+	pub const fn from_bits(bits: u16) -> PinSet {
+		PinSet(bits)
+	}
+
+	/// Gets the bitmask of the set, where bit `n` is pin `n`.
+	pub const fn bits(self) -> u16 {
+		self.0
+	}
+
+	/// Gets the bit for `pin`, or zero if `pin > 15`.
+	const fn bit(pin: u8) -> u16 {
+		match 1u16.checked_shl(pin as u32) {
+			Some(bit) => bit,
+			None => 0,
+		}
+	}
+
+	/// Does the set contain `pin`?
+	///
+	/// Always returns `false` if `pin > 15`.
+	pub const fn contains(self, pin: u8) -> bool {
+		self.0 & Self::bit(pin) != 0
+	}
+
+	/// Adds `pin` to the set. Pins above 15 are ignored.
+	pub fn insert(&mut self, pin: u8) {
+		self.0 |= Self::bit(pin);
+	}
+
+	/// Removes `pin` from the set.
+	pub fn remove(&mut self, pin: u8) {
+		self.0 &= !Self::bit(pin);
+	}
+
+	/// The number of pins in the set.
+	pub const fn len(self) -> usize {
+		self.0.count_ones() as usize
+	}
+
+	/// Is the set empty?
+	pub const fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Iterates over the pins in the set, in ascending order.
+	pub fn iter(self) -> PinSetIter {
+		PinSetIter(self.0)
+	}
+}
+
+impl From<u16> for PinSet {
+	fn from(bits: u16) -> PinSet {
+		PinSet(bits)
+	}
+}
+
+impl From<PinSet> for u16 {
+	fn from(set: PinSet) -> u16 {
+		set.0
+	}
+}
+
+impl FromIterator<u8> for PinSet {
+	fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> PinSet {
+		let mut set = PinSet::empty();
+		set.extend(iter);
+		set
+	}
+}
+
+impl Extend<u8> for PinSet {
+	fn extend<T: IntoIterator<Item = u8>>(&mut self, iter: T) {
+		for pin in iter {
+			self.insert(pin);
+		}
+	}
+}
+
+impl IntoIterator for PinSet {
+	type Item = u8;
+	type IntoIter = PinSetIter;
+
+	fn into_iter(self) -> PinSetIter {
+		self.iter()
+	}
+}
+
+impl fmt::Debug for PinSet {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.iter()).finish()
+	}
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PinSet {
+	fn format(&self, f: defmt::Formatter) {
+		defmt::write!(f, "PinSet({=u16:#018b})", self.0)
+	}
+}
+
+macro_rules! set_op {
+	($op:ident, $fn:ident, $assign:ident, $assign_fn:ident, |$a:ident, $b:ident| $expr:expr) => {
+		impl $op for PinSet {
+			type Output = PinSet;
+
+			fn $fn(self, rhs: PinSet) -> PinSet {
+				let ($a, $b) = (self.0, rhs.0);
+				PinSet($expr)
+			}
+		}
+
+		impl $assign for PinSet {
+			fn $assign_fn(&mut self, rhs: PinSet) {
+				*self = $op::$fn(*self, rhs);
+			}
+		}
+	};
+}
+
+set_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| a | b);
+set_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| a & b);
+set_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| a ^ b);
+set_op!(Sub, sub, SubAssign, sub_assign, |a, b| a & !b);
+
+impl Not for PinSet {
+	type Output = PinSet;
+
+	fn not(self) -> PinSet {
+		PinSet(!self.0)
+	}
+}
+
+/// An iterator over the pins in a [`PinSet`], in ascending order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PinSetIter(u16);
+
+impl PinSetIter {
+	/// The pins which have not been returned yet.
+	pub(crate) fn remaining(&self) -> PinSet {
+		PinSet(self.0)
+	}
+}
+
+impl Iterator for PinSetIter {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		if self.0 == 0 {
+			return None;
+		}
+		let pin = self.0.trailing_zeros() as u8;
+		self.0 &= self.0 - 1;
+		Some(pin)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.0.count_ones() as usize;
+		(len, Some(len))
+	}
+}
+
+impl ExactSizeIterator for PinSetIter {}
+
+impl FusedIterator for PinSetIter {}
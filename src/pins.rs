@@ -1,246 +1,668 @@
-use crate::{Error, PinMode, Polarity, Register, Stmpe1600};
+use crate::{
+	Error, InterruptInput, NoInterruptPin, PinMode, Polarity, Register, Stmpe1600, Trigger,
+};
+use core::fmt::Debug;
 use core::marker::PhantomData;
-use embedded_hal::blocking::i2c::{Read, Write};
-use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::i2c::I2c;
 
+/// Type states for the mode a [`Pin`] is configured in.
 pub mod modes {
+	/// Floating input.
 	pub struct Input;
+	/// Push-pull output.
 	pub struct Output;
+	/// Input which triggers the interrupt output pin when it changes.
 	pub struct Interrupt;
 }
 use modes::*;
 
-/// A single I/O pin on the STMPE1600.
-///
-/// `Pin` takes a `MODE` as a generic argument, which is either `Input`, `Output` or `Interrupt`,
-/// and indicates which mode of operation the current pin is configured for. This mode can be
-/// changed by using the `into_input_pin`, `into_output_pin` and `into_interrupt_pin` functions
-/// respectively.
+mod sealed {
+	pub trait Sealed {}
+}
+
+/// The index of a pin, either known at runtime ([`u8`]) or at compile time ([`PinNum`]).
 ///
-/// Input and interrupt pins implement the trait [`embedded_hal::digital::v2::InputPin`], and output
-/// pins implement [`embedded_hal::digital::v2::OutputPin`]. This means that the pins on the I/O
-/// expander can be used by platform agnostic drivers as if they were regular GPIO pins.
+/// This trait is sealed, and cannot be implemented outside of this crate.
+pub trait PinIndex: sealed::Sealed + Copy {
+	/// The index of the pin, from 0 to 15.
+	fn index(&self) -> u8;
+
+	/// The bit mask of the pin in the STMPE1600's registers.
+	fn mask(&self) -> u16 {
+		1 << self.index()
+	}
+}
+
+impl sealed::Sealed for u8 {}
+
+impl PinIndex for u8 {
+	fn index(&self) -> u8 {
+		*self
+	}
+}
+
+/// A pin index known at compile time.
 ///
-/// # Examples
+/// Using a `PinNum` with an index of 16 or more is rejected when the crate using it is compiled,
+/// and the pin's bit mask is a constant.
 ///
-/// ## Changing pin mode
-/// ```rust,ignore
-/// use embedded_hal::digital::v2::{InputPin, OutputPin};
-/// use linux_embedded_hal::I2cdev;
-/// use stmpe1600::Stmpe1600Builder;
+/// The index is checked when the bit mask is evaluated, which only happens when the code is built.
+/// `cargo check` stops before that point, so it accepts an out of range `PinNum`, and the error
+/// is only reported by `cargo build`.
 ///
-/// # fn main() -> Result<(), stmpe1600::Error<linux_embedded_hal::i2cdev::linux::LinuxI2CError>> {
-/// let dev = I2cdev::new("/dev/i2c-1").unwrap();
-/// let stmpe1600 = Stmpe1600Builder::new(dev)
-/// 	.build()
-/// 	.expect("Could not initialise STMPE1600 driver");
+/// ```rust,compile_fail
+/// use stmpe1600::{PinIndex, PinNum};
 ///
-/// let mut input_pin = stmpe1600.pin_input(0)?;
-/// let _ = input_pin.is_high()?;
-/// let mut output_pin = input_pin.into_output_pin()?;
-/// output_pin.set_high()?;
-/// # Ok(())
-/// # }
+/// let mask = PinNum::<16>.mask();
 /// ```
-///
-/// ## Setting interrupt polarity
-/// ```rust,ignore
-/// use linux_embedded_hal::I2cdev;
-/// use stmpe1600::{Polarity, Stmpe1600Builder};
-///
-/// # fn main() -> Result<(), stmpe1600::Error<linux_embedded_hal::i2cdev::linux::LinuxI2CError>> {
-/// let dev = I2cdev::new("/dev/i2c-1").unwrap();
-/// let stmpe1600 = Stmpe1600Builder::new(dev)
-/// 	.build()
-/// 	.expect("Could not initialise STMPE1600 driver");
-///
-/// let mut input_pin = stmpe1600.pin_input(0)?;
-/// input_pin.set_interrupt_polarity(Polarity::High)?;
-/// # Ok(())
-/// # }
-/// ```
-pub struct Pin<'a, I2C, MODE> {
-	driver: &'a Stmpe1600<I2C>,
-	pin: u8,
-	_phantom: PhantomData<MODE>,
+#[derive(Clone, Copy, Debug)]
+pub struct PinNum<const N: u8>;
+
+impl<const N: u8> PinNum<N> {
+	const MASK: u16 = {
+		assert!(N < 16, "STMPE1600 pin index out of range");
+		1 << N
+	};
 }
 
-impl<'a, E, I2C, MODE> Pin<'a, I2C, MODE>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
-	pub(crate) fn new(driver: &'a Stmpe1600<I2C>, pin: u8) -> Pin<'a, I2C, MODE> {
-		Pin {
-			driver,
-			pin,
-			_phantom: PhantomData,
-		}
+impl<const N: u8> sealed::Sealed for PinNum<N> {}
+
+impl<const N: u8> PinIndex for PinNum<N> {
+	fn index(&self) -> u8 {
+		let _ = Self::MASK;
+		N
 	}
 
-	/// Get the polarity inversion of the current pin.
-	pub fn polarity_inversion(&mut self) -> Result<Polarity, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let gppir = dev.read_reg(Register::GPPIR)?;
-		if gppir & (1 << self.pin) == (1 << self.pin) {
-			Ok(Polarity::High)
-		} else {
-			Ok(Polarity::Low)
-		}
+	fn mask(&self) -> u16 {
+		Self::MASK
 	}
+}
 
-	/// Set the polarity inversion of the current pin.
-	pub fn set_polarity_inversion(&mut self, polarity: Polarity) -> Result<(), Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut gppir = dev.read_reg(Register::GPPIR)?;
-		match polarity {
-			Polarity::Low => gppir &= !(1 << self.pin),
-			Polarity::High => gppir |= 1 << self.pin,
+/// Defines the `P0` to `P15` aliases of the `Pin` type in scope, and the `Parts` struct which holds
+/// all 16 of them, so that the blocking and async drivers are split in the same way.
+macro_rules! define_parts {
+	() => {
+		define_parts! {
+			0 P0 p0, 1 P1 p1, 2 P2 p2, 3 P3 p3, 4 P4 p4, 5 P5 p5, 6 P6 p6, 7 P7 p7,
+			8 P8 p8, 9 P9 p9, 10 P10 p10, 11 P11 p11, 12 P12 p12, 13 P13 p13, 14 P14 p14, 15 P15 p15
 		}
-		dev.write_reg(Register::GPPIR, gppir)?;
-		Ok(())
-	}
+	};
+	($($n:literal $alias:ident $field:ident),+) => {
+		$(
+			#[doc = concat!("Pin ", $n, " of the STMPE1600, with its index known at compile time.")]
+			pub type $alias<'a, I2C, MODE, INT = NoInterruptPin> = Pin<'a, I2C, MODE, PinNum<$n>, INT>;
+		)+
+
+		/// All 16 pins of the STMPE1600, as returned by [`Stmpe1600::split`].
+		///
+		/// Each pin is an independent handle, so different pins can be given to different drivers at
+		/// the same time.
+		pub struct Parts<'a, I2C, INT = NoInterruptPin> {
+			$(
+				#[doc = concat!("Pin ", $n, ".")]
+				pub $field: $alias<'a, I2C, Input, INT>,
+			)+
+		}
+
+		impl<'a, I2C, INT> Parts<'a, I2C, INT> {
+			pub(crate) fn new(driver: &'a Stmpe1600<I2C, INT>) -> Parts<'a, I2C, INT> {
+				Parts {
+					$($field: Pin::new(driver, PinNum),)+
+				}
+			}
+		}
+	};
 }
+#[cfg(feature = "async")]
+pub(crate) use define_parts;
 
-impl<'a, E, I2C> Pin<'a, I2C, Input>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
-	/// Configure the pin as an output pin.
-	pub fn into_output_pin(self) -> Result<Pin<'a, I2C, Output>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut gpdr = dev.read_reg(Register::GPDR)?;
-		gpdr |= 1 << self.pin;
-		dev.write_reg(Register::GPDR, gpdr)?;
+define_parts!();
 
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Output;
-		Ok(Pin::new(self.driver, self.pin))
-	}
+/// Defines the `Pin` type and implements its functions, for one way of accessing the bus, as
+/// `impl_driver!` does for the driver.
+///
+/// The attributes given are applied to the `Pin` struct. The `embedded-hal` traits, which differ
+/// between the blocking and async pins, are implemented alongside each of them instead.
+macro_rules! impl_pin {
+	($(#[$attr:meta])* [$($async:tt)?], [$($await:tt)*]) => {
+		$(#[$attr])*
+		pub struct Pin<'a, I2C, MODE, P = u8, INT = NoInterruptPin> {
+			driver: &'a Stmpe1600<I2C, INT>,
+			pin: P,
+			_phantom: PhantomData<MODE>,
+		}
 
-	/// Configure the pin as an interrupt pin.
-	pub fn into_interrupt_pin(self) -> Result<Pin<'a, I2C, Interrupt>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut iegpior = dev.read_reg(Register::IEGPIOR)?;
-		iegpior |= 1 << self.pin;
-		dev.write_reg(Register::IEGPIOR, iegpior)?;
+		impl<'a, I2C, MODE, P, INT> Pin<'a, I2C, MODE, P, INT>
+		where
+			P: PinIndex,
+		{
+			pub(crate) fn new(driver: &'a Stmpe1600<I2C, INT>, pin: P) -> Pin<'a, I2C, MODE, P, INT> {
+				Pin {
+					driver,
+					pin,
+					_phantom: PhantomData,
+				}
+			}
 
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Interrupt;
-		Ok(Pin::new(self.driver, self.pin))
-	}
+			/// Get the index of the current pin.
+			pub fn index(&self) -> u8 {
+				self.pin.index()
+			}
+
+			/// Converts the pin into one whose index is only known at runtime.
+			pub fn erase(self) -> Pin<'a, I2C, MODE, u8, INT> {
+				Pin::new(self.driver, self.pin.index())
+			}
+		}
+
+		impl<'a, E, I2C, MODE, P, INT> Pin<'a, I2C, MODE, P, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+			P: PinIndex,
+		{
+			$($async)? fn into_mode<NEW>(self, mode: PinMode) -> Result<Pin<'a, I2C, NEW, P, INT>, Error<E>> {
+				self.try_into_mode(mode)$($await)*.map_err(|(error, _)| error)
+			}
+
+			/// Changes the mode of the pin, handing the pin back along with the error if that fails.
+			///
+			/// The pin's mode is only recorded once every register has been written, so the pin
+			/// which is handed back can be converted again.
+			$($async)? fn try_into_mode<NEW>(
+				self,
+				mode: PinMode,
+			) -> Result<Pin<'a, I2C, NEW, P, INT>, (Error<E>, Self)> {
+				match self.driver.set_mode(self.pin.index(), mode)$($await)* {
+					Ok(()) => Ok(Pin::new(self.driver, self.pin)),
+					Err(error) => Err((error, self)),
+				}
+			}
+
+			/// Get the polarity inversion of the current pin.
+			pub $($async)? fn polarity_inversion(&mut self) -> Result<Polarity, Error<E>> {
+				let mut dev = self.driver.device()$($await)*?;
+				let gppir = dev.read_reg_cached(Register::GPPIR)$($await)*?;
+				if gppir & self.pin.mask() != 0 {
+					Ok(Polarity::High)
+				} else {
+					Ok(Polarity::Low)
+				}
+			}
+
+			/// Set the polarity inversion of the current pin.
+			pub $($async)? fn set_polarity_inversion(&mut self, polarity: Polarity) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.update_reg(Register::GPPIR, self.pin.mask(), polarity == Polarity::High)
+					$($await)*
+			}
+
+			$($async)? fn read_level(&self) -> Result<bool, Error<E>> {
+				let mask = self.driver.device()$($await)*?.read_reg(Register::GPMR)$($await)*?;
+				Ok(mask & self.pin.mask() != 0)
+			}
+
+			/// Reads back the level the pin is set to drive from the output register, rather than
+			/// the level on the pin itself.
+			$($async)? fn output_level(&self) -> Result<bool, Error<E>> {
+				let gpsr = self
+					.driver
+					.device()
+					$($await)*?
+					.read_reg_cached(Register::GPSR)
+					$($await)*?;
+				Ok(gpsr & self.pin.mask() != 0)
+			}
+
+			$($async)? fn toggle_level(&self) -> Result<(), Error<E>> {
+				let mask = self.pin.mask();
+				self.driver
+					.device()
+					$($await)*?
+					.modify_reg(Register::GPSR, |gpsr| gpsr ^ mask)
+					$($await)*
+			}
+
+			$($async)? fn write_level(&self, high: bool) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.update_reg(Register::GPSR, self.pin.mask(), high)
+					$($await)*
+			}
+		}
+
+		impl<'a, E, I2C, MODE, P, INT> ErrorType for Pin<'a, I2C, MODE, P, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+			E: Debug,
+		{
+			type Error = Error<E>;
+		}
+
+		impl<'a, E, I2C, P, INT> Pin<'a, I2C, Input, P, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+			P: PinIndex,
+		{
+			/// Configure the pin as an output pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_output_pin`](Pin::try_into_output_pin) to get it back along with the error.
+			pub $($async)? fn into_output_pin(self) -> Result<Pin<'a, I2C, Output, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Output)$($await)*
+			}
+
+			/// Configure the pin as an output pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_output_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Output, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Output)$($await)*
+			}
+
+			/// Configure the pin as an interrupt pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_interrupt_pin`](Pin::try_into_interrupt_pin) to get it back along with the error.
+			pub $($async)? fn into_interrupt_pin(self) -> Result<Pin<'a, I2C, Interrupt, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Interrupt)$($await)*
+			}
+
+			/// Configure the pin as an interrupt pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_interrupt_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Interrupt, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Interrupt)$($await)*
+			}
+		}
+
+		impl<'a, E, I2C, P, INT> Pin<'a, I2C, Output, P, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+			P: PinIndex,
+		{
+			/// Configure the pin as an input pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_input_pin`](Pin::try_into_input_pin) to get it back along with the error.
+			pub $($async)? fn into_input_pin(self) -> Result<Pin<'a, I2C, Input, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Input)$($await)*
+			}
+
+			/// Configure the pin as an input pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_input_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Input, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Input)$($await)*
+			}
+
+			/// Configure the pin as an interrupt pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_interrupt_pin`](Pin::try_into_interrupt_pin) to get it back along with the error.
+			pub $($async)? fn into_interrupt_pin(self) -> Result<Pin<'a, I2C, Interrupt, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Interrupt)$($await)*
+			}
+
+			/// Configure the pin as an interrupt pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_interrupt_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Interrupt, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Interrupt)$($await)*
+			}
+		}
+
+		impl<'a, E, I2C, P, INT> Pin<'a, I2C, Interrupt, P, INT>
+		where
+			I2C: I2c<Error = E>,
+			INT: InterruptInput,
+			P: PinIndex,
+		{
+			/// Configure the pin as an input pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_input_pin`](Pin::try_into_input_pin) to get it back along with the error.
+			pub $($async)? fn into_input_pin(self) -> Result<Pin<'a, I2C, Input, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Input)$($await)*
+			}
+
+			/// Configure the pin as an input pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_input_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Input, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Input)$($await)*
+			}
+
+			/// Configure the pin as an output pin.
+			///
+			/// If this fails, the pin is lost; use
+			/// [`try_into_output_pin`](Pin::try_into_output_pin) to get it back along with the error.
+			pub $($async)? fn into_output_pin(self) -> Result<Pin<'a, I2C, Output, P, INT>, Error<E>> {
+				self.into_mode(PinMode::Output)$($await)*
+			}
+
+			/// Configure the pin as an output pin, handing the pin back along with the error if
+			/// that fails, so that the change can be retried.
+			pub $($async)? fn try_into_output_pin(
+				self,
+			) -> Result<Pin<'a, I2C, Output, P, INT>, (Error<E>, Self)> {
+				self.try_into_mode(PinMode::Output)$($await)*
+			}
+
+			/// Get the edges of the pin which are reported as interrupts.
+			pub $($async)? fn trigger(&self) -> Result<Trigger, Error<E>> {
+				Ok(self.driver.device()$($await)*?.trigger(self.pin.index()))
+			}
+
+			/// Set the edges of the pin which are reported as interrupts.
+			///
+			/// The STMPE1600 still raises an interrupt on every change of the pin, but edges which
+			/// do not match `trigger` are filtered out of
+			/// [`get_interrupts`](Stmpe1600::get_interrupts) and
+			/// [`get_interrupt_events`](Stmpe1600::get_interrupt_events). Each time a pin is made
+			/// an interrupt pin, its trigger starts out as [`Trigger::Both`].
+			pub $($async)? fn set_trigger(&mut self, trigger: Trigger) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.set_trigger(self.pin.index(), trigger);
+				Ok(())
+			}
+
+			/// Masks the pin's interrupt, so that it no longer raises interrupts, without changing
+			/// its mode.
+			pub $($async)? fn mask(&mut self) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.update_reg(Register::IEGPIOR, self.pin.mask(), false)
+					$($await)*
+			}
+
+			/// Unmasks the pin's interrupt, after it has been masked with [`mask`](Pin::mask).
+			pub $($async)? fn unmask(&mut self) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.update_reg(Register::IEGPIOR, self.pin.mask(), true)
+					$($await)*
+			}
+
+			/// Is the pin's interrupt masked?
+			pub $($async)? fn is_masked(&self) -> Result<bool, Error<E>> {
+				let iegpior = self
+					.driver
+					.device()
+					$($await)*?
+					.read_reg_cached(Register::IEGPIOR)
+					$($await)*?;
+				Ok(iegpior & self.pin.mask() == 0)
+			}
+
+			/// Does the pin have an interrupt pending?
+			///
+			/// This reads the interrupt status from the STMPE1600. As that clears every pending
+			/// bit, the interrupts of all pins are latched by the driver, so that no other pin
+			/// loses its interrupt. The interrupt stays pending until it is cleared with
+			/// [`clear_pending`](Pin::clear_pending), or taken by [`Stmpe1600::get_interrupts`]
+			/// or [`Stmpe1600::get_interrupt_events`].
+			///
+			/// ```rust,ignore
+			/// if button.is_pending()? {
+			///     button.clear_pending()?;
+			///     // Handle the button press.
+			/// }
+			/// ```
+			pub $($async)? fn is_pending(&mut self) -> Result<bool, Error<E>> {
+				let dev = self.driver.latch_interrupts(true)$($await)*?;
+				Ok(dev.is_latched(self.pin.index()))
+			}
+
+			/// Clears the pending interrupt of the pin, if there is one.
+			///
+			/// This only clears the interrupt latched by the driver, and does not talk to the
+			/// STMPE1600.
+			pub $($async)? fn clear_pending(&mut self) -> Result<(), Error<E>> {
+				self.driver
+					.device()
+					$($await)*?
+					.clear_pending(self.pin.index());
+				Ok(())
+			}
+		}
+	};
+}
+#[cfg(feature = "async")]
+pub(crate) use impl_pin;
+
+impl_pin! {
+	/// A single I/O pin on the STMPE1600.
+	///
+	/// `Pin` takes a `MODE` as a generic argument, which is either `Input`, `Output` or `Interrupt`,
+	/// and indicates which mode of operation the current pin is configured for. This mode can be
+	/// changed by using the `into_input_pin`, `into_output_pin` and `into_interrupt_pin` functions
+	/// respectively.
+	///
+	/// The `P` argument is the pin's index. By default this is a [`u8`] chosen at runtime, which is
+	/// convenient for code which iterates over pins. Pins returned by [`Stmpe1600::split`] instead
+	/// use a [`PinNum`], which fixes the index at compile time; these can be converted to runtime
+	/// indexed pins with [`erase`](Pin::erase).
+	///
+	/// Input and interrupt pins implement the trait [`embedded_hal::digital::InputPin`], and output
+	/// pins implement [`embedded_hal::digital::OutputPin`] and
+	/// [`embedded_hal::digital::StatefulOutputPin`]. This means that the pins on the I/O expander can
+	/// be used by platform agnostic drivers as if they were regular GPIO pins. Output pins report the
+	/// level they are set to drive, which is read from GPSR (or the register cache) rather than GPMR.
+	/// With the `eh02` feature enabled, the `embedded-hal` 0.2 `digital::v2` traits are implemented
+	/// as well.
+	///
+	/// # Examples
+	///
+	/// ## Changing pin mode
+	/// ```rust,ignore
+	/// use embedded_hal::digital::{InputPin, OutputPin};
+	/// use linux_embedded_hal::I2cdev;
+	/// use stmpe1600::Stmpe1600Builder;
+	///
+	/// # fn main() -> Result<(), stmpe1600::Error<linux_embedded_hal::I2CError>> {
+	/// let dev = I2cdev::new("/dev/i2c-1").unwrap();
+	/// let mut stmpe1600 = Stmpe1600Builder::new(dev)
+	///     .build()
+	///     .expect("Could not initialise STMPE1600 driver");
+	///
+	/// let mut input_pin = stmpe1600.pin_input(0)?;
+	/// let _ = input_pin.is_high()?;
+	/// let mut output_pin = input_pin.into_output_pin()?;
+	/// output_pin.set_high()?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// ## Setting polarity inversion
+	/// The polarity inversion applies to a single pin. The polarity of the interrupt output pin is
+	/// set for the whole device, with [`Stmpe1600::set_interrupt_polarity`].
+	/// ```rust,ignore
+	/// use linux_embedded_hal::I2cdev;
+	/// use stmpe1600::{Polarity, Stmpe1600Builder};
+	///
+	/// # fn main() -> Result<(), stmpe1600::Error<linux_embedded_hal::I2CError>> {
+	/// let dev = I2cdev::new("/dev/i2c-1").unwrap();
+	/// let mut stmpe1600 = Stmpe1600Builder::new(dev)
+	///     .build()
+	///     .expect("Could not initialise STMPE1600 driver");
+	///
+	/// let mut input_pin = stmpe1600.pin_input(0)?;
+	/// input_pin.set_polarity_inversion(Polarity::High)?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	[], []
 }
 
-impl<'a, E, I2C> InputPin for Pin<'a, I2C, Input>
+impl<'a, E, I2C, P, INT> InputPin for Pin<'a, I2C, Input, P, INT>
 where
-	I2C: Read<Error = E> + Write<Error = E>,
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+	E: Debug,
 {
-	type Error = Error<E>;
-
-	fn is_low(&self) -> Result<bool, Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPMR)?;
-		Ok(mask & (1 << self.pin) == 0)
+	fn is_high(&mut self) -> Result<bool, Self::Error> {
+		self.read_level()
 	}
 
-	fn is_high(&self) -> Result<bool, Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPMR)?;
-		Ok(mask & (1 << self.pin) == 1 << self.pin)
+	fn is_low(&mut self) -> Result<bool, Self::Error> {
+		Ok(!self.read_level()?)
 	}
 }
 
-impl<'a, E, I2C> Pin<'a, I2C, Output>
+impl<'a, E, I2C, P, INT> OutputPin for Pin<'a, I2C, Output, P, INT>
 where
-	I2C: Read<Error = E> + Write<Error = E>,
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+	E: Debug,
 {
-	/// Configure the pin as an input pin.
-	pub fn into_input_pin(self) -> Result<Pin<'a, I2C, Input>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut gpdr = dev.read_reg(Register::GPDR)?;
-		gpdr &= !(1 << self.pin);
-		dev.write_reg(Register::GPDR, gpdr)?;
-
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Input;
-		Ok(Pin::new(self.driver, self.pin))
-	}
-
-	/// Configure the pin as an interrupt pin.
-	pub fn into_interrupt_pin(self) -> Result<Pin<'a, I2C, Interrupt>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut gpdr = dev.read_reg(Register::GPDR)?;
-		gpdr &= !(1 << self.pin);
-		dev.write_reg(Register::GPDR, gpdr)?;
-		let mut iegpior = dev.read_reg(Register::IEGPIOR)?;
-		iegpior |= 1 << self.pin;
-		dev.write_reg(Register::IEGPIOR, iegpior)?;
-
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Interrupt;
-		Ok(Pin::new(self.driver, self.pin))
+	fn set_low(&mut self) -> Result<(), Self::Error> {
+		self.write_level(false)
+	}
+
+	fn set_high(&mut self) -> Result<(), Self::Error> {
+		self.write_level(true)
 	}
 }
 
-impl<'a, E, I2C> OutputPin for Pin<'a, I2C, Output>
+impl<'a, E, I2C, P, INT> StatefulOutputPin for Pin<'a, I2C, Output, P, INT>
 where
-	I2C: Read<Error = E> + Write<Error = E>,
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	E: Debug,
+	P: PinIndex,
 {
-	type Error = Error<E>;
+	fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+		self.output_level()
+	}
 
-	fn set_low(&mut self) -> Result<(), Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPSR)?;
-		self.driver
-			.device
-			.borrow_mut()
-			.write_reg(Register::GPSR, mask & !(1 << self.pin))
+	fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+		Ok(!self.output_level()?)
 	}
 
-	fn set_high(&mut self) -> Result<(), Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPSR)?;
-		self.driver
-			.device
-			.borrow_mut()
-			.write_reg(Register::GPSR, mask | (1 << self.pin))
+	fn toggle(&mut self) -> Result<(), Self::Error> {
+		self.toggle_level()
 	}
 }
 
-impl<'a, E, I2C> Pin<'a, I2C, Interrupt>
+impl<'a, E, I2C, P, INT> InputPin for Pin<'a, I2C, Interrupt, P, INT>
 where
-	I2C: Read<Error = E> + Write<Error = E>,
+	I2C: I2c<Error = E>,
+	INT: InterruptInput,
+	P: PinIndex,
+	E: Debug,
 {
-	/// Configure the pin as an input pin.
-	pub fn into_input_pin(self) -> Result<Pin<'a, I2C, Input>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut iegpior = dev.read_reg(Register::IEGPIOR)?;
-		iegpior &= !(1 << self.pin);
-		dev.write_reg(Register::IEGPIOR, iegpior)?;
-
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Input;
-		Ok(Pin::new(self.driver, self.pin))
-	}
-
-	/// Configure the pin as an output pin.
-	pub fn into_output_pin(self) -> Result<Pin<'a, I2C, Output>, Error<E>> {
-		let mut dev = self.driver.device.borrow_mut();
-		let mut gpdr = dev.read_reg(Register::GPDR)?;
-		gpdr |= 1 << self.pin;
-		dev.write_reg(Register::GPDR, gpdr)?;
-		let mut iegpior = dev.read_reg(Register::IEGPIOR)?;
-		iegpior &= !(1 << self.pin);
-		dev.write_reg(Register::IEGPIOR, iegpior)?;
-
-		self.driver.pins.borrow_mut()[self.pin as usize] = PinMode::Output;
-		Ok(Pin::new(self.driver, self.pin))
+	fn is_high(&mut self) -> Result<bool, Self::Error> {
+		self.read_level()
+	}
+
+	fn is_low(&mut self) -> Result<bool, Self::Error> {
+		Ok(!self.read_level()?)
 	}
 }
 
-impl<'a, E, I2C> InputPin for Pin<'a, I2C, Interrupt>
-where
-	I2C: Read<Error = E> + Write<Error = E>,
-{
-	type Error = Error<E>;
+#[cfg(feature = "eh02")]
+mod eh02 {
+	use super::{Input, Interrupt, Output, Pin, PinIndex};
+	use crate::{Error, InterruptInput};
+	use embedded_hal::i2c::I2c;
+	use embedded_hal_02::digital::v2::{
+		InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin,
+	};
+
+	impl<'a, E, I2C, P, INT> InputPin for Pin<'a, I2C, Input, P, INT>
+	where
+		I2C: I2c<Error = E>,
+		INT: InterruptInput,
+		P: PinIndex,
+	{
+		type Error = Error<E>;
+
+		fn is_high(&self) -> Result<bool, Self::Error> {
+			self.read_level()
+		}
+
+		fn is_low(&self) -> Result<bool, Self::Error> {
+			Ok(!self.read_level()?)
+		}
+	}
+
+	impl<'a, E, I2C, P, INT> OutputPin for Pin<'a, I2C, Output, P, INT>
+	where
+		I2C: I2c<Error = E>,
+		INT: InterruptInput,
+		P: PinIndex,
+	{
+		type Error = Error<E>;
+
+		fn set_low(&mut self) -> Result<(), Self::Error> {
+			self.write_level(false)
+		}
+
+		fn set_high(&mut self) -> Result<(), Self::Error> {
+			self.write_level(true)
+		}
+	}
+
+	impl<'a, E, I2C, P, INT> StatefulOutputPin for Pin<'a, I2C, Output, P, INT>
+	where
+		I2C: I2c<Error = E>,
+		INT: InterruptInput,
+		P: PinIndex,
+	{
+		fn is_set_high(&self) -> Result<bool, Self::Error> {
+			self.output_level()
+		}
 
-	fn is_low(&self) -> Result<bool, Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPMR)?;
-		Ok(mask & (1 << self.pin) == 0)
+		fn is_set_low(&self) -> Result<bool, Self::Error> {
+			Ok(!self.output_level()?)
+		}
 	}
 
-	fn is_high(&self) -> Result<bool, Self::Error> {
-		let mask = self.driver.device.borrow_mut().read_reg(Register::GPMR)?;
-		Ok(mask & (1 << self.pin) == 1 << self.pin)
+	impl<'a, E, I2C, P, INT> ToggleableOutputPin for Pin<'a, I2C, Output, P, INT>
+	where
+		I2C: I2c<Error = E>,
+		INT: InterruptInput,
+		P: PinIndex,
+	{
+		type Error = Error<E>;
+
+		fn toggle(&mut self) -> Result<(), Self::Error> {
+			self.toggle_level()
+		}
+	}
+
+	impl<'a, E, I2C, P, INT> InputPin for Pin<'a, I2C, Interrupt, P, INT>
+	where
+		I2C: I2c<Error = E>,
+		INT: InterruptInput,
+		P: PinIndex,
+	{
+		type Error = Error<E>;
+
+		fn is_high(&self) -> Result<bool, Self::Error> {
+			self.read_level()
+		}
+
+		fn is_low(&self) -> Result<bool, Self::Error> {
+			Ok(!self.read_level()?)
+		}
 	}
 }
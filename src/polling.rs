@@ -0,0 +1,62 @@
+//! Change detection by polling the pin states, for boards where the interrupt output is not wired.
+
+use crate::PinSet;
+
+/// Detects changes on a set of pins by comparing successive samples of their levels.
+///
+/// This produces the same [`InterruptEvents`](crate::InterruptEvents) as [`Stmpe1600::get_interrupt_events`](crate::Stmpe1600::get_interrupt_events),
+/// so the same application code can handle them whether or not the STMPE1600's interrupt output
+/// is connected to the host. Pass the detector to [`Stmpe1600::poll_changes`](crate::Stmpe1600::poll_changes)
+/// periodically; the first call only records the initial levels, and reports no events.
+///
+/// Changes which happen and revert between two polls are not seen, so the polling interval must
+/// be shorter than the shortest pulse of interest.
+///
+/// ```rust,ignore
+/// use stmpe1600::{ChangeDetector, PinSet};
+///
+/// let mut detector = ChangeDetector::new(PinSet::from_bits(0x000F));
+/// loop {
+///     for event in stmpe1600.poll_changes(&mut detector)? {
+///         // Handle `event` exactly as if it came from `get_interrupt_events`.
+///     }
+///     delay.delay_ms(10);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ChangeDetector {
+	pins: PinSet,
+	previous: Option<u16>,
+}
+
+impl ChangeDetector {
+	/// Creates a detector which watches the pins in `pins`.
+	pub const fn new(pins: PinSet) -> ChangeDetector {
+		ChangeDetector {
+			pins,
+			previous: None,
+		}
+	}
+
+	/// The pins which are watched for changes.
+	pub fn pins(&self) -> PinSet {
+		self.pins
+	}
+
+	/// Forgets the last sample, so that the next one only records the levels again.
+	pub fn reset(&mut self) {
+		self.previous = None;
+	}
+
+	/// Records a new sample of the pin levels, where the pins in `levels` are high, and returns
+	/// the pins which changed since the previous sample.
+	pub(crate) fn update(&mut self, levels: PinSet) -> u16 {
+		let levels = levels.bits();
+		let changed = match self.previous {
+			Some(previous) => (previous ^ levels) & self.pins.bits(),
+			None => 0,
+		};
+		self.previous = Some(levels);
+		changed
+	}
+}
@@ -0,0 +1,217 @@
+//! A fixed-capacity queue of interrupt events, for handing them from an interrupt handler to the
+//! main loop.
+
+use crate::device::Stmpe1600Device;
+use crate::{Error, InterruptEvent, InterruptEvents, PinSet};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use embedded_hal::i2c::I2c;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+/// A fixed-capacity queue of [`InterruptEvent`]s, with a single producer and a single consumer.
+///
+/// The queue is split into an [`EventProducer`], which is filled in the host's interrupt handler
+/// by an [`InterruptReader`], and an [`EventConsumer`], which is drained by the main loop. Neither
+/// half needs a lock, so the main loop can drain events while the interrupt handler is filling
+/// the queue.
+///
+/// The queue holds up to `N - 1` events. Events which arrive while it is full are dropped, and
+/// counted by [`EventConsumer::overflows`].
+///
+/// The queue must outlive both halves, so it is usually kept in a `static`, for example with the
+/// [`static_cell`](https://crates.io/crates/static_cell) crate:
+///
+/// ```rust,ignore
+/// use static_cell::StaticCell;
+/// use stmpe1600::EventQueue;
+///
+/// static QUEUE: StaticCell<EventQueue<16>> = StaticCell::new();
+/// let (mut producer, mut consumer) = QUEUE.init(EventQueue::new()).split();
+/// // Move `producer` into the interrupt handler, along with an `InterruptReader`.
+///
+/// // In the main loop:
+/// while let Some(event) = consumer.dequeue() {
+///     // ...
+/// }
+/// ```
+pub struct EventQueue<const N: usize> {
+	queue: Queue<InterruptEvent, N>,
+	overflows: AtomicUsize,
+}
+
+impl<const N: usize> EventQueue<N> {
+	/// Creates an empty queue.
+	pub const fn new() -> EventQueue<N> {
+		EventQueue {
+			queue: Queue::new(),
+			overflows: AtomicUsize::new(0),
+		}
+	}
+
+	/// Splits the queue into the handle which fills it and the handle which drains it.
+	pub fn split(&mut self) -> (EventProducer<'_, N>, EventConsumer<'_, N>) {
+		let (producer, consumer) = self.queue.split();
+		(
+			EventProducer {
+				producer,
+				overflows: &self.overflows,
+			},
+			EventConsumer {
+				consumer,
+				overflows: &self.overflows,
+			},
+		)
+	}
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+	fn default() -> EventQueue<N> {
+		EventQueue::new()
+	}
+}
+
+/// The half of an [`EventQueue`] which is filled by [`InterruptReader::read_into`], or by
+/// [`Stmpe1600::queue_interrupt_events`](crate::Stmpe1600::queue_interrupt_events).
+pub struct EventProducer<'a, const N: usize> {
+	producer: Producer<'a, InterruptEvent, N>,
+	overflows: &'a AtomicUsize,
+}
+
+impl<'a, const N: usize> EventProducer<'a, N> {
+	/// Adds an event to the queue, or counts it as an overflow if the queue is full.
+	///
+	/// Returns whether the event was queued.
+	pub(crate) fn push(&mut self, event: InterruptEvent) -> bool {
+		if self.producer.enqueue(event).is_ok() {
+			return true;
+		}
+		// This is the only writer, so the count does not need an atomic read-modify-write, which
+		// some targets lack.
+		let overflows = self.overflows.load(Ordering::Relaxed);
+		self.overflows
+			.store(overflows.wrapping_add(1), Ordering::Relaxed);
+		false
+	}
+
+	/// Adds every event to the queue, and returns the set of pins whose events were queued.
+	pub(crate) fn push_events(&mut self, events: InterruptEvents) -> PinSet {
+		let mut queued = PinSet::empty();
+		for event in events {
+			if self.push(event) {
+				queued.insert(event.pin);
+			}
+		}
+		queued
+	}
+}
+
+/// The half of an [`EventQueue`] which is drained by the main loop.
+pub struct EventConsumer<'a, const N: usize> {
+	consumer: Consumer<'a, InterruptEvent, N>,
+	overflows: &'a AtomicUsize,
+}
+
+impl<'a, const N: usize> EventConsumer<'a, N> {
+	/// Takes the oldest event from the queue, if there is one.
+	pub fn dequeue(&mut self) -> Option<InterruptEvent> {
+		self.consumer.dequeue()
+	}
+
+	/// The number of events in the queue.
+	pub fn len(&self) -> usize {
+		self.consumer.len()
+	}
+
+	/// Is the queue empty?
+	pub fn is_empty(&self) -> bool {
+		!self.consumer.ready()
+	}
+
+	/// The number of events which have been dropped because the queue was full.
+	///
+	/// The count is never reset, and wraps around on overflow, so compare it against an earlier
+	/// value to find out whether events have been dropped since then.
+	pub fn overflows(&self) -> usize {
+		self.overflows.load(Ordering::Relaxed)
+	}
+}
+
+/// Reads the STMPE1600's pending interrupts into an [`EventQueue`] from the host's interrupt
+/// handler, while the driver is used from the main loop.
+///
+/// The driver keeps its state in `RefCell`s, so it cannot be shared with an interrupt handler.
+/// The reader is a separate, minimal handle which only reads the interrupt status and pin state
+/// registers, so it can be moved into the interrupt handler on its own. It needs its own handle
+/// to the I²C bus, shared with the driver through an adapter which makes each transfer atomic,
+/// such as `CriticalSectionDevice` from the
+/// [`embedded-hal-bus`](https://crates.io/crates/embedded-hal-bus) crate. Every register read is a
+/// single write-read transfer, so the reader can safely interrupt the driver between any two
+/// transfers.
+///
+/// Every pending interrupt is queued, whatever the pins' [`Trigger`](crate::Trigger)s are. The
+/// driver's own functions which read interrupts, such as
+/// [`get_interrupts`](crate::Stmpe1600::get_interrupts), should not be used alongside a reader,
+/// as each would take interrupts the other is expecting.
+///
+/// ```rust,ignore
+/// use core::cell::RefCell;
+/// use critical_section::Mutex;
+/// use embedded_hal_bus::i2c::CriticalSectionDevice;
+/// use static_cell::StaticCell;
+/// use stmpe1600::{EventQueue, InterruptReader, Polarity, Stmpe1600Builder, DEFAULT_ADDRESS};
+///
+/// static BUS: StaticCell<Mutex<RefCell<I2c>>> = StaticCell::new();
+/// static QUEUE: StaticCell<EventQueue<16>> = StaticCell::new();
+///
+/// let bus = BUS.init(Mutex::new(RefCell::new(i2c)));
+/// let stmpe1600 = Stmpe1600Builder::new(CriticalSectionDevice::new(bus))
+///     .pin_interrupt(0)
+///     .interrupts(Polarity::Low)
+///     .build()?;
+/// let mut reader = InterruptReader::new(CriticalSectionDevice::new(bus), DEFAULT_ADDRESS);
+/// let (mut producer, mut consumer) = QUEUE.init(EventQueue::new()).split();
+///
+/// // Move `reader` and `producer` into the interrupt handler, which calls:
+/// reader.read_into(&mut producer)?;
+///
+/// // In the main loop:
+/// while let Some(event) = consumer.dequeue() {
+///     // ...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InterruptReader<I2C> {
+	device: Stmpe1600Device<I2C>,
+}
+
+impl<I2C> InterruptReader<I2C> {
+	/// Creates a reader for the STMPE1600 at `address`, without talking to it.
+	pub fn new(i2c: I2C, address: u8) -> InterruptReader<I2C> {
+		InterruptReader {
+			device: Stmpe1600Device::from_parts(i2c, address, false),
+		}
+	}
+
+	/// Consumes the reader, and returns its handle to the I²C bus.
+	pub fn release(self) -> I2C {
+		self.device.release()
+	}
+}
+
+impl<I2C, E> InterruptReader<I2C>
+where
+	I2C: I2c<Error = E>,
+{
+	/// Reads the pending interrupts, and adds an [`InterruptEvent`] for each of them to the queue
+	/// which `producer` fills.
+	///
+	/// Every pending interrupt is consumed; those which do not fit in the queue are dropped, and
+	/// counted by [`EventConsumer::overflows`]. Returns the set of pins whose events were queued.
+	pub fn read_into<const N: usize>(
+		&mut self,
+		producer: &mut EventProducer<'_, N>,
+	) -> Result<PinSet, Error<E>> {
+		self.device.read_interrupts(true)?;
+		let events = self.device.take_interrupt_events(PinSet::all());
+		Ok(producer.push_events(events))
+	}
+}
@@ -0,0 +1,74 @@
+//! Interrupt events stamped with the time they were read, from a clock supplied by the application.
+
+use crate::{InterruptEvent, InterruptEvents};
+
+/// A monotonic clock, used to stamp interrupt events with the time they were read.
+///
+/// The clock counts in ticks of whatever length suits the application (for example milliseconds
+/// since boot), and must never go backwards. It is implemented for any `FnMut() -> u64`, so a
+/// closure reading the host's timer can be used directly.
+///
+/// ```rust,ignore
+/// let mut clock = || timer.now().ticks();
+/// for stamped in stmpe1600.get_timestamped_events(&mut clock)? {
+///     // `stamped.timestamp` is when `stamped.event` was read.
+/// }
+/// ```
+pub trait Clock {
+	/// The current time, in ticks.
+	fn now(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Clock for F {
+	fn now(&mut self) -> u64 {
+		self()
+	}
+}
+
+/// An [`InterruptEvent`], along with the time it was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampedEvent {
+	/// The event.
+	pub event: InterruptEvent,
+	/// The time the event was read, in ticks of the [`Clock`].
+	pub timestamp: u64,
+}
+
+/// An iterator over [`TimestampedEvent`]s, as returned by
+/// [`Stmpe1600::get_timestamped_events`](crate::Stmpe1600::get_timestamped_events).
+///
+/// Every event was decoded from the same read of the STMPE1600, so they all share one timestamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampedEvents {
+	events: InterruptEvents,
+	timestamp: u64,
+}
+
+impl TimestampedEvents {
+	pub(crate) fn new(events: InterruptEvents, timestamp: u64) -> TimestampedEvents {
+		TimestampedEvents { events, timestamp }
+	}
+
+	/// The time the events were read, in ticks of the [`Clock`].
+	pub fn timestamp(&self) -> u64 {
+		self.timestamp
+	}
+}
+
+impl Iterator for TimestampedEvents {
+	type Item = TimestampedEvent;
+
+	fn next(&mut self) -> Option<TimestampedEvent> {
+		let event = self.events.next()?;
+		Some(TimestampedEvent {
+			event,
+			timestamp: self.timestamp,
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.events.size_hint()
+	}
+}
+
+impl ExactSizeIterator for TimestampedEvents {}
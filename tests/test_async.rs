@@ -0,0 +1,347 @@
+#![cfg(feature = "async")]
+
+use embassy_futures::join::{join, join3};
+use embassy_futures::{block_on, yield_now};
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+use embedded_hal_mock::eh1::digital::{
+	Mock as PinMock, State as PinMockState, Transaction as PinTransaction,
+};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use stmpe1600::{Error, Polarity, Stmpe1600Builder, Trigger, DEFAULT_ADDRESS};
+
+#[test]
+fn read_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Get pin 0 state.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+	]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut input_pin = stmpe1600.pin_input(0).await.unwrap();
+		assert!(input_pin.is_high().await.unwrap(), "Input pin in is LOW");
+	});
+	i2c.done();
+}
+
+#[test]
+fn write_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 0 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		// Set pin 0 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
+	]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut output_pin = stmpe1600.pin_output(0).await.unwrap();
+		output_pin.set_high().await.unwrap();
+	});
+	i2c.done();
+}
+
+#[test]
+fn toggle_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 0 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		// Toggle pin 0.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
+	]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut output_pin = stmpe1600.pin_output(0).await.unwrap();
+		assert!(output_pin.is_set_low().await.unwrap());
+		output_pin.toggle().await.unwrap();
+		assert!(output_pin.is_set_high().await.unwrap());
+	});
+	i2c.done();
+}
+
+#[test]
+fn const_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 15 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x80]),
+		// Set pin 15 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x00, 0x80]),
+	]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut output_pin = stmpe1600.pin_output_const::<15>().await.unwrap();
+		assert_eq!(output_pin.index(), 15);
+		output_pin.set_high().await.unwrap();
+	});
+	i2c.done();
+}
+
+#[test]
+fn interrupt_trigger() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 2 as an interrupt pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x04, 0x00]),
+	]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut pin = stmpe1600.pin_interrupt(2).await.unwrap();
+		assert_eq!(pin.trigger().await.unwrap(), Trigger::Both);
+		pin.set_trigger(Trigger::Rising).await.unwrap();
+		assert_eq!(pin.trigger().await.unwrap(), Trigger::Rising);
+		pin.clear_pending().await.unwrap();
+	});
+	i2c.done();
+}
+
+#[test]
+fn interrupt_polarity() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Enable interrupts with an active HIGH output.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x05]),
+	]);
+
+	block_on(async {
+		Stmpe1600Builder::new(i2c.clone())
+			.interrupts(Polarity::High)
+			.build_async()
+			.await
+			.unwrap();
+	});
+	i2c.done();
+}
+
+#[test]
+fn destroy() {
+	let i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Software reset before releasing the bus.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+	]);
+
+	let (mut i2c, _) = block_on(async {
+		let stmpe1600 = Stmpe1600Builder::new(i2c).build_async().await.unwrap();
+		stmpe1600.destroy_all().await.unwrap()
+	});
+	i2c.done();
+}
+
+#[test]
+fn wait_for_edges() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Enable the interrupt output, active low.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x04]),
+		// Set pins 0 and 1 as interrupt pins.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x03, 0x00]),
+		// Pin 1 is already high.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x02, 0x00]),
+		// Once the interrupt output is asserted, read the interrupt status and pin levels once for
+		// both waiting pins.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x03, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x00]),
+	]);
+	let mut int = PinMock::new(&[PinTransaction::wait_for_state(PinMockState::Low)]);
+
+	block_on(async {
+		let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.interrupts(Polarity::Low)
+			.interrupt_pin(int.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let parts = stmpe1600.split().await.unwrap();
+		assert!(matches!(stmpe1600.split().await, Err(Error::AlreadySplit)));
+		let mut p0 = parts.p0.into_interrupt_pin().await.unwrap();
+		let mut p1 = parts.p1.into_interrupt_pin().await.unwrap();
+		p1.wait_for_high().await.unwrap();
+		let (falling, any) = join(p0.wait_for_falling_edge(), p1.wait_for_any_edge()).await;
+		falling.unwrap();
+		any.unwrap();
+	});
+	i2c.done();
+	int.done();
+}
+
+#[test]
+fn wait_for_unreported_edges() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes, with pin 0 as an interrupt pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x00]),
+		// Enable the interrupt output, active low.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x04]),
+		// Pin 0 is high, so waiting for it to be low needs a falling edge.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+		// Mask pin 0.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	]);
+	// The host pin is never waited on.
+	let mut int = PinMock::new(&[]);
+
+	block_on(async {
+		let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+			.pin_interrupt(0)
+			.interrupts(Polarity::Low)
+			.interrupt_pin(int.clone())
+			.build_async()
+			.await
+			.unwrap();
+		let mut p0 = stmpe1600.pin_interrupt_const::<0>().await.unwrap();
+		p0.set_trigger(Trigger::Rising).await.unwrap();
+		assert!(matches!(
+			p0.wait_for_falling_edge().await,
+			Err(Error::EdgeFiltered)
+		));
+		assert!(matches!(p0.wait_for_low().await, Err(Error::EdgeFiltered)));
+		p0.set_trigger(Trigger::Both).await.unwrap();
+		p0.mask().await.unwrap();
+		assert!(matches!(
+			p0.wait_for_any_edge().await,
+			Err(Error::PinMasked)
+		));
+	});
+	i2c.done();
+	int.done();
+}
+
+/// An I²C bus which lets other tasks run before each transaction.
+struct YieldingI2c(I2cMock);
+
+impl ErrorType for YieldingI2c {
+	type Error = <I2cMock as ErrorType>::Error;
+}
+
+impl I2c for YieldingI2c {
+	async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+		yield_now().await;
+		self.0.write(address, write).await
+	}
+
+	async fn write_read(
+		&mut self,
+		address: u8,
+		write: &[u8],
+		read: &mut [u8],
+	) -> Result<(), Self::Error> {
+		yield_now().await;
+		self.0.write_read(address, write, read).await
+	}
+
+	async fn transaction(
+		&mut self,
+		address: u8,
+		operations: &mut [Operation<'_>],
+	) -> Result<(), Self::Error> {
+		yield_now().await;
+		self.0.transaction(address, operations).await
+	}
+}
+
+#[test]
+fn split_concurrently() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Read the port while both splits wait for the device.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+	]);
+
+	block_on(async {
+		let stmpe1600 = Stmpe1600Builder::new(YieldingI2c(i2c.clone()))
+			.build_async()
+			.await
+			.unwrap();
+		let (port, first, second) =
+			join3(stmpe1600.read_port(), stmpe1600.split(), stmpe1600.split()).await;
+		assert_eq!(port.unwrap().bits(), 0x0001);
+		assert!(first.is_ok(), "First split failed");
+		assert!(matches!(second, Err(Error::AlreadySplit)));
+	});
+	i2c.done();
+}
@@ -1,33 +1,161 @@
-use embedded_hal_mock::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
-use stmpe1600::{Stmpe1600Builder, DEFAULT_ADDRESS};
+use embedded_hal::digital::{OutputPin, PinState};
+use embedded_hal::i2c::ErrorKind;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use stmpe1600::{Error, Polarity, Stmpe1600Builder, DEFAULT_ADDRESS};
 
 #[test]
 fn basic_builder() {
 	let expectations = [
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x00]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x16]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
 	];
-	let i2c = I2cMock::new(&expectations);
-	let _stmpe1600 = Stmpe1600Builder::new(i2c)
+	let mut i2c = I2cMock::new(&expectations);
+	let _stmpe1600 = Stmpe1600Builder::new(i2c.clone())
 		.build()
 		.expect("Failed to initialise STMPE1600 driver");
+	i2c.done();
 }
 
 #[test]
 fn custom_address_builder() {
 	let expectations = [
-		I2cTransaction::write(0x43, vec![0x00]),
-		I2cTransaction::read(0x43, vec![0x00, 0x16]),
+		I2cTransaction::write_read(0x43, vec![0x00], vec![0x00, 0x16]),
 		I2cTransaction::write(0x43, vec![0x03, 0x80]),
 		I2cTransaction::write(0x43, vec![0x14, 0x00, 0x00]),
 		I2cTransaction::write(0x43, vec![0x08, 0x00, 0x00]),
 	];
-	let i2c = I2cMock::new(&expectations);
-	let _stmpe1600 = Stmpe1600Builder::new(i2c)
+	let mut i2c = I2cMock::new(&expectations);
+	let _stmpe1600 = Stmpe1600Builder::new(i2c.clone())
 		.address(0x43)
 		.build()
 		.expect("Failed to initialise STMPE1600 driver with custom address");
+	i2c.done();
+}
+
+#[test]
+fn pin_config_builder() {
+	let expectations = [
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Output levels, then polarity inversion, then direction, then interrupt enable.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16, 0x00, 0x02]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x03, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x02]),
+		// Interrupt output is enabled last.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x04]),
+		// Pin 1 is already an output, so only its level is written.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x03, 0x00]),
+	];
+	let mut i2c = I2cMock::new(&expectations);
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_output(0, PinState::High)
+		.pin_output(1, PinState::Low)
+		.pin_interrupt(9)
+		.polarity_inversion(9, Polarity::High)
+		.interrupts(Polarity::Low)
+		.build()
+		.expect("Failed to initialise STMPE1600 driver with pin configuration");
+	stmpe1600.pin_output(1).unwrap().set_high().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn invalid_pin_builder() {
+	let i2c = I2cMock::new(&[
+		// Nothing is sent until the bus is handed back and used again.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	]);
+	let (error, i2c, _) = match Stmpe1600Builder::new(i2c)
+		.pin_output(16, PinState::High)
+		.try_build()
+	{
+		Ok(_) => panic!("Invalid pin was accepted"),
+		Err(failed) => failed,
+	};
+	assert!(matches!(error, Error::InvalidPin));
+	let stmpe1600 = Stmpe1600Builder::new(i2c)
+		.build()
+		.expect("Failed to initialise STMPE1600 driver with the returned bus");
+	let mut i2c = stmpe1600.release();
+	i2c.done();
+}
+
+#[test]
+fn invalid_device_id() {
+	let i2c = I2cMock::new(&[
+		// Something other than an STMPE1600 answers, and the bus is handed back.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x01, 0x16]),
+	]);
+	let (error, mut i2c, _) = match Stmpe1600Builder::new(i2c).try_build() {
+		Ok(_) => panic!("Invalid device ID was accepted"),
+		Err(failed) => failed,
+	};
+	assert!(matches!(error, Error::InvalidDeviceID));
+	i2c.done();
+}
+
+#[test]
+fn release() {
+	let expectations = [
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	];
+	let i2c = I2cMock::new(&expectations);
+	let stmpe1600 = Stmpe1600Builder::new(i2c)
+		.pin_output(0, PinState::Low)
+		.build()
+		.expect("Failed to initialise STMPE1600 driver");
+	let mut i2c = stmpe1600.release();
+	i2c.done();
+}
+
+#[test]
+fn destroy() {
+	let expectations = [
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Software reset before releasing the bus.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+	];
+	let i2c = I2cMock::new(&expectations);
+	let stmpe1600 = Stmpe1600Builder::new(i2c)
+		.pin_output(0, PinState::Low)
+		.build()
+		.expect("Failed to initialise STMPE1600 driver");
+	let mut i2c = stmpe1600.destroy().expect("Failed to reset STMPE1600");
+	i2c.done();
+}
+
+#[test]
+fn destroy_failed() {
+	let expectations = [
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// The software reset fails, but the bus is still returned.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]).with_error(ErrorKind::Other),
+	];
+	let i2c = I2cMock::new(&expectations);
+	let stmpe1600 = Stmpe1600Builder::new(i2c)
+		.pin_output(0, PinState::Low)
+		.build()
+		.expect("Failed to initialise STMPE1600 driver");
+	let (error, mut i2c) = match stmpe1600.destroy() {
+		Ok(_) => panic!("Reset did not fail"),
+		Err(failed) => failed,
+	};
+	assert!(matches!(error, Error::I2CError(ErrorKind::Other)));
+	i2c.done();
 }
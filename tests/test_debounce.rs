@@ -0,0 +1,68 @@
+use embedded_hal::digital::PinState;
+use stmpe1600::{
+	ButtonAction, ButtonEvent, Debouncer, Edge, InterruptEvent, PinSet, TimestampedEvent,
+};
+
+fn edge(pin: u8, level: PinState, timestamp: u64) -> TimestampedEvent {
+	let edge = match level {
+		PinState::High => Edge::Rising,
+		PinState::Low => Edge::Falling,
+	};
+	TimestampedEvent {
+		event: InterruptEvent { pin, level, edge },
+		timestamp,
+	}
+}
+
+#[test]
+fn bouncing_press_and_release() {
+	let mut debouncer = Debouncer::new(10);
+
+	// Pin 2 bounces, then settles high.
+	debouncer.update(edge(2, PinState::High, 100));
+	debouncer.update(edge(2, PinState::Low, 102));
+	debouncer.update(edge(2, PinState::High, 105));
+	assert_eq!(debouncer.next_deadline(), Some(115));
+	assert_eq!(debouncer.poll(114).count(), 0);
+	assert_eq!(
+		debouncer.poll(115).collect::<Vec<_>>(),
+		[ButtonEvent {
+			pin: 2,
+			action: ButtonAction::Press,
+			timestamp: 105,
+		}]
+	);
+	assert_eq!(debouncer.pressed(), PinSet::from_bits(0b0100));
+	assert_eq!(debouncer.next_deadline(), None);
+
+	// A glitch which ends up back where it started reports nothing.
+	debouncer.update(edge(2, PinState::Low, 200));
+	debouncer.update(edge(2, PinState::High, 201));
+	assert_eq!(debouncer.poll(300).count(), 0);
+
+	debouncer.update(edge(2, PinState::Low, 400));
+	assert_eq!(
+		debouncer.poll(410).collect::<Vec<_>>(),
+		[ButtonEvent {
+			pin: 2,
+			action: ButtonAction::Release,
+			timestamp: 400,
+		}]
+	);
+	assert_eq!(debouncer.pressed(), PinSet::empty());
+}
+
+#[test]
+fn per_pin_windows() {
+	let mut debouncer = Debouncer::new(10);
+	debouncer.set_window(5, 50);
+	assert_eq!(debouncer.window(5), 50);
+	assert_eq!(debouncer.window(16), 0);
+
+	debouncer.update(edge(0, PinState::High, 0));
+	debouncer.update(edge(5, PinState::High, 0));
+	assert_eq!(debouncer.next_deadline(), Some(10));
+	assert_eq!(debouncer.poll(10).pending(), PinSet::from_bits(0x0001));
+	assert_eq!(debouncer.next_deadline(), Some(50));
+	assert_eq!(debouncer.poll(50).pending(), PinSet::from_bits(0x0020));
+}
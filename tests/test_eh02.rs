@@ -0,0 +1,91 @@
+#![cfg(feature = "eh02")]
+
+use embedded_hal_02::digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin};
+use embedded_hal_mock::eh0::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use stmpe1600::compat::I2cCompat;
+use stmpe1600::{Stmpe1600Builder, DEFAULT_ADDRESS};
+
+#[test]
+fn read_write_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Get pin 0 state.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+		// Set pin 1 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x02, 0x00]),
+		// Set pin 1 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x02, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(I2cCompat::new(i2c.clone()))
+		.build()
+		.unwrap();
+	assert!(stmpe1600.pin_input(0).unwrap().is_high().unwrap());
+	stmpe1600.pin_output(1).unwrap().set_high().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn toggle_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 2 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x04, 0x00]),
+		// Toggle pin 2.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x04, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(I2cCompat::new(i2c.clone()))
+		.build()
+		.unwrap();
+	let mut output_pin = stmpe1600.pin_output(2).unwrap();
+	assert!(output_pin.is_set_low().unwrap());
+	output_pin.toggle().unwrap();
+	assert!(output_pin.is_set_high().unwrap());
+	i2c.done();
+}
+
+#[test]
+fn compat_transactions() {
+	use embedded_hal::i2c::{I2c, Operation};
+	use stmpe1600::compat::CompatError;
+
+	let mut i2c = I2cMock::new(&[I2cTransaction::write_read(
+		DEFAULT_ADDRESS,
+		vec![0x10],
+		vec![0x01, 0x00],
+	)]);
+	let mut compat = I2cCompat::new(i2c.clone());
+
+	// A write followed by a read is a single 0.2 write_read, with a repeated start.
+	let mut buffer = [0u8; 2];
+	compat
+		.write_read(DEFAULT_ADDRESS, &[0x10], &mut buffer)
+		.unwrap();
+	assert_eq!(buffer, [0x01, 0x00]);
+
+	// Sequences the 0.2 traits cannot perform are rejected without touching the bus.
+	let mut buffer = [0u8; 1];
+	let result = compat.transaction(
+		DEFAULT_ADDRESS,
+		&mut [
+			Operation::Write(&[0x10]),
+			Operation::Write(&[0x12]),
+			Operation::Read(&mut buffer),
+		],
+	);
+	assert!(matches!(result, Err(CompatError::Unsupported)));
+	i2c.done();
+}
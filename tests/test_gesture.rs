@@ -0,0 +1,152 @@
+use stmpe1600::{
+	ButtonAction, ButtonEvent, Gesture, GestureConfig, GestureDetector, GestureEvent, PinSet,
+};
+
+const CONFIG: GestureConfig = GestureConfig {
+	double_click: 300,
+	long_press: Some(1000),
+	repeat: Some(200),
+};
+
+fn press(pin: u8, timestamp: u64) -> ButtonEvent {
+	ButtonEvent {
+		pin,
+		action: ButtonAction::Press,
+		timestamp,
+	}
+}
+
+fn release(pin: u8, timestamp: u64) -> ButtonEvent {
+	ButtonEvent {
+		pin,
+		action: ButtonAction::Release,
+		timestamp,
+	}
+}
+
+fn gesture(pin: u8, gesture: Gesture, timestamp: u64) -> GestureEvent {
+	GestureEvent {
+		pin,
+		gesture,
+		timestamp,
+	}
+}
+
+#[test]
+fn clicks() {
+	let mut detector = GestureDetector::new(CONFIG);
+
+	// A single click is only reported once the double click window has passed.
+	assert_eq!(detector.update(press(0, 0)), None);
+	assert_eq!(detector.update(release(0, 100)), None);
+	assert_eq!(detector.next_deadline(), Some(400));
+	assert_eq!(detector.poll(399).count(), 0);
+	assert_eq!(
+		detector.poll(400).collect::<Vec<_>>(),
+		[gesture(0, Gesture::Click, 100)]
+	);
+	assert_eq!(detector.next_deadline(), None);
+
+	// A second press inside the window makes a double click.
+	detector.update(press(0, 1000));
+	detector.update(release(0, 1100));
+	detector.update(press(0, 1300));
+	assert_eq!(
+		detector.update(release(0, 1400)),
+		Some(gesture(0, Gesture::DoubleClick, 1400))
+	);
+	assert_eq!(detector.poll(10_000).count(), 0);
+
+	// A second press after the window, before the detector is polled, reports the first click.
+	detector.update(press(0, 20_000));
+	detector.update(release(0, 20_100));
+	assert_eq!(
+		detector.update(press(0, 20_400)),
+		Some(gesture(0, Gesture::Click, 20_100))
+	);
+
+	// Without a double click window, clicks are reported on release.
+	detector.set_config(
+		1,
+		GestureConfig {
+			double_click: 0,
+			..CONFIG
+		},
+	);
+	detector.update(press(1, 0));
+	assert_eq!(
+		detector.update(release(1, 50)),
+		Some(gesture(1, Gesture::Click, 50))
+	);
+}
+
+#[test]
+fn long_press_and_repeat() {
+	let mut detector = GestureDetector::new(CONFIG);
+	detector.set_config(
+		3,
+		GestureConfig {
+			repeat: None,
+			..CONFIG
+		},
+	);
+
+	detector.update(press(2, 0));
+	detector.update(press(3, 0));
+	assert_eq!(detector.next_deadline(), Some(1000));
+	let events = detector.poll(1000);
+	assert_eq!(events.pending(), PinSet::from_bits(0b1100));
+	assert_eq!(
+		events.collect::<Vec<_>>(),
+		[
+			gesture(2, Gesture::LongPress, 1000),
+			gesture(3, Gesture::LongPress, 1000),
+		]
+	);
+
+	// Only pin 2 repeats, and overdue repeats are skipped.
+	assert_eq!(detector.next_deadline(), Some(1200));
+	assert_eq!(
+		detector.poll(1650).collect::<Vec<_>>(),
+		[gesture(2, Gesture::Repeat, 1200)]
+	);
+	assert_eq!(detector.next_deadline(), Some(1800));
+
+	// Releasing after a long press is not a click.
+	assert_eq!(detector.update(release(2, 1700)), None);
+	assert_eq!(detector.update(release(3, 1700)), None);
+	assert_eq!(detector.next_deadline(), None);
+}
+
+#[test]
+fn repeat_far_overdue() {
+	let mut detector = GestureDetector::new(CONFIG);
+	detector.update(press(0, 0));
+	assert_eq!(
+		detector.poll(1000).collect::<Vec<_>>(),
+		[gesture(0, Gesture::LongPress, 1000)]
+	);
+
+	// A huge gap skips straight to the first repeat after it.
+	let now = 1200 + 200 * 1_000_000_000_000 + 50;
+	assert_eq!(
+		detector.poll(now).collect::<Vec<_>>(),
+		[gesture(0, Gesture::Repeat, 1200)]
+	);
+	assert_eq!(detector.next_deadline(), Some(now + 150));
+}
+
+#[test]
+fn repeat_at_end_of_clock() {
+	let mut detector = GestureDetector::new(CONFIG);
+	detector.update(press(0, u64::MAX - 1000));
+	assert_eq!(detector.next_deadline(), Some(u64::MAX));
+	assert_eq!(
+		detector.poll(u64::MAX).collect::<Vec<_>>(),
+		[gesture(0, Gesture::LongPress, u64::MAX)]
+	);
+
+	// The next repeat would be past the end of the clock, so there are no more.
+	assert_eq!(detector.next_deadline(), None);
+	assert_eq!(detector.poll(u64::MAX).count(), 0);
+}
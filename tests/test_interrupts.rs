@@ -0,0 +1,484 @@
+use core::sync::atomic::{AtomicU16, Ordering};
+use embedded_hal::digital::PinState;
+use embedded_hal_mock::eh1::digital::{
+	Mock as PinMock, State as PinMockState, Transaction as PinTransaction,
+};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use stmpe1600::{
+	ChangeDetector, Edge, Error, EventQueue, InterruptEvent, InterruptOutput, InterruptReader,
+	PinSet, Polarity, Stmpe1600Builder, TimestampedEvent, Trigger, DEFAULT_ADDRESS,
+};
+
+fn init() -> Vec<I2cTransaction> {
+	vec![
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x01]),
+	]
+}
+
+#[test]
+fn interrupt_events() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read the interrupt status, then the pin levels.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	let events = stmpe1600.get_interrupt_events().unwrap();
+	assert_eq!(events.len(), 2);
+	assert_eq!(
+		events.collect::<Vec<_>>(),
+		[
+			InterruptEvent {
+				pin: 0,
+				level: PinState::Low,
+				edge: Edge::Falling,
+			},
+			InterruptEvent {
+				pin: 8,
+				level: PinState::High,
+				edge: Edge::Rising,
+			},
+		]
+	);
+	i2c.done();
+}
+
+#[test]
+fn timestamped_events() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read the interrupt status, then the pin levels.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	let mut ticks = 41;
+	let mut clock = || {
+		ticks += 1;
+		ticks
+	};
+	let events = stmpe1600.get_timestamped_events(&mut clock).unwrap();
+	assert_eq!(events.timestamp(), 42);
+	assert_eq!(
+		events.collect::<Vec<_>>(),
+		[TimestampedEvent {
+			event: InterruptEvent {
+				pin: 8,
+				level: PinState::High,
+				edge: Edge::Rising,
+			},
+			timestamp: 42,
+		}]
+	);
+	i2c.done();
+}
+
+#[test]
+fn no_interrupt_events() {
+	let mut expectations = init();
+	expectations.extend([
+		// Nothing is pending, so the pin levels are not read.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	assert_eq!(stmpe1600.get_interrupt_events().unwrap().next(), None);
+	i2c.done();
+}
+
+#[test]
+fn trigger_filtering() {
+	let mut expectations = init();
+	expectations.extend([
+		// Both pins rose, but pin 0 only reports falling edges.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x01]),
+		// Both pins fell, but pin 8 only reports rising edges.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	let mut falling = stmpe1600.pin_interrupt(0).unwrap();
+	assert_eq!(falling.trigger().unwrap(), Trigger::Both);
+	falling.set_trigger(Trigger::Falling).unwrap();
+	let mut rising = stmpe1600.pin_interrupt(8).unwrap();
+	rising.set_trigger(Trigger::Rising).unwrap();
+
+	let events = stmpe1600.get_interrupt_events().unwrap();
+	assert_eq!(events.map(|event| event.pin).collect::<Vec<_>>(), [8]);
+	let pending = stmpe1600.get_interrupts().unwrap();
+	assert!(pending.contains(0));
+	assert!(!pending.contains(8));
+	i2c.done();
+}
+
+#[test]
+fn trigger_both_skips_levels() {
+	let mut expectations = init();
+	expectations.extend([
+		// Pin 8 reports both edges, so the pin levels are not needed.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	stmpe1600
+		.pin_interrupt(0)
+		.unwrap()
+		.set_trigger(Trigger::Rising)
+		.unwrap();
+	let pending = stmpe1600.get_interrupts().unwrap();
+	assert_eq!(pending, PinSet::from_bits(0x0100));
+	i2c.done();
+}
+
+#[test]
+fn pending_interrupts_are_latched() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pins 0 and 8 as interrupt pins.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x01]),
+		// Pin 0 reads the interrupt status, latching pin 8's interrupt as well.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x01]),
+		// Nothing new is pending for the remaining checks.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+	]);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let pins = stmpe1600.split().unwrap();
+	let mut p0 = pins.p0.into_interrupt_pin().unwrap();
+	let mut p8 = pins.p8.into_interrupt_pin().unwrap();
+	assert!(p0.is_pending().unwrap());
+	assert!(p8.is_pending().unwrap());
+	p8.clear_pending().unwrap();
+	assert!(!p8.is_pending().unwrap());
+	assert!(p0.is_pending().unwrap());
+	// Pin 0's interrupt was never cleared, so it is still reported, with its latched level.
+	assert_eq!(
+		stmpe1600
+			.get_interrupt_events()
+			.unwrap()
+			.collect::<Vec<_>>(),
+		[InterruptEvent {
+			pin: 0,
+			level: PinState::Low,
+			edge: Edge::Falling,
+		}]
+	);
+	i2c.done();
+}
+
+static RISING: AtomicU16 = AtomicU16::new(0);
+
+fn record_rising(event: InterruptEvent) {
+	if event.edge == Edge::Rising {
+		RISING.fetch_or(1 << event.pin, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn dispatch_handlers() {
+	let mut expectations = init();
+	expectations.extend([
+		// Both pins fired, but only pin 0 has a handler.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x01]),
+		// Pin 8's interrupt is still latched.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	stmpe1600.set_handler(0, record_rising).unwrap();
+	assert!(matches!(
+		stmpe1600.set_handler(16, record_rising),
+		Err(Error::InvalidPin)
+	));
+	assert_eq!(stmpe1600.dispatch().unwrap(), PinSet::from_bits(0x0001));
+	assert_eq!(RISING.load(Ordering::SeqCst), 0x0001);
+	assert_eq!(
+		stmpe1600.get_interrupts().unwrap(),
+		PinSet::from_bits(0x0100)
+	);
+	i2c.done();
+}
+
+#[test]
+fn interrupt_output_control() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read back the configuration.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x05]),
+		// Change the polarity, leaving the output enabled.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x05]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x04]),
+		// Disable the output, keeping its polarity.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x04]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x00]),
+		// Enable it again, active high.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x05]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	assert_eq!(
+		stmpe1600.interrupt_output().unwrap(),
+		InterruptOutput {
+			enabled: true,
+			polarity: Polarity::High,
+		}
+	);
+	stmpe1600.set_interrupt_polarity(Polarity::Low).unwrap();
+	stmpe1600.disable_interrupt_output().unwrap();
+	stmpe1600.enable_interrupt_output(Polarity::High).unwrap();
+	i2c.done();
+}
+
+#[test]
+fn host_interrupt_pin() {
+	let mut expectations = init();
+	expectations.extend([
+		// Enable the interrupt output, active low.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x04]),
+		// The interrupt status is only read once the host pin is asserted.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x00]),
+		// Disable the interrupt output.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x03], vec![0x04]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x00]),
+	]);
+	let i2c = I2cMock::new(&expectations);
+	let int = PinMock::new(&[
+		PinTransaction::get(PinMockState::High),
+		PinTransaction::get(PinMockState::High),
+		PinTransaction::get(PinMockState::Low),
+	]);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c)
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.interrupts(Polarity::Low)
+		.interrupt_pin(int)
+		.build()
+		.unwrap();
+	assert!(!stmpe1600.interrupt_asserted().unwrap());
+	assert_eq!(stmpe1600.get_interrupts().unwrap(), PinSet::empty());
+	assert_eq!(
+		stmpe1600.get_interrupts().unwrap(),
+		PinSet::from_bits(0x0001)
+	);
+	// With the output disabled, the host pin says nothing, so is not read.
+	stmpe1600.disable_interrupt_output().unwrap();
+	assert!(stmpe1600.interrupt_asserted().unwrap());
+	// Both the bus and the host pin are handed back.
+	let (mut i2c, mut int) = stmpe1600.release_all();
+	i2c.done();
+	int.done();
+}
+
+#[test]
+fn queue_events() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read the interrupt status, then the pin levels.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	// Room for a single event.
+	let mut queue = EventQueue::<2>::new();
+	let (mut producer, mut consumer) = queue.split();
+	assert_eq!(
+		stmpe1600.queue_interrupt_events(&mut producer).unwrap(),
+		PinSet::from_bits(0x0001)
+	);
+	assert_eq!(consumer.len(), 1);
+	assert_eq!(consumer.overflows(), 1);
+	assert_eq!(
+		consumer.dequeue(),
+		Some(InterruptEvent {
+			pin: 0,
+			level: PinState::Low,
+			edge: Edge::Falling,
+		})
+	);
+	assert!(consumer.is_empty());
+	i2c.done();
+}
+
+#[test]
+fn interrupt_reader() {
+	let mut expectations = init();
+	expectations.extend([
+		// The reader reads the interrupt status, then the pin levels.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x01, 0x01]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x00, 0x01]),
+		// The driver carries on using the bus.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x02, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	let mut reader = InterruptReader::new(i2c.clone(), DEFAULT_ADDRESS);
+	let mut queue = EventQueue::<4>::new();
+	let (mut producer, mut consumer) = queue.split();
+	assert_eq!(
+		reader.read_into(&mut producer).unwrap(),
+		PinSet::from_bits(0x0101)
+	);
+	stmpe1600.write_port(PinSet::from_bits(0x0002)).unwrap();
+	assert_eq!(
+		consumer.dequeue(),
+		Some(InterruptEvent {
+			pin: 0,
+			level: PinState::Low,
+			edge: Edge::Falling,
+		})
+	);
+	assert_eq!(
+		consumer.dequeue(),
+		Some(InterruptEvent {
+			pin: 8,
+			level: PinState::High,
+			edge: Edge::Rising,
+		})
+	);
+	i2c.done();
+}
+
+#[test]
+fn mask_interrupts() {
+	let mut expectations = init();
+	expectations.extend([
+		// Mask and unmask pin 0.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x01]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x01]),
+		// Mask every interrupt pin, then restore them.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x01, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	let mut pin = stmpe1600.pin_interrupt(0).unwrap();
+	assert!(!pin.is_masked().unwrap());
+	pin.mask().unwrap();
+	assert!(pin.is_masked().unwrap());
+	pin.unmask().unwrap();
+
+	// Only interrupt pins are affected by the bulk mask.
+	stmpe1600.set_interrupt_mask(PinSet::all()).unwrap();
+	assert_eq!(
+		stmpe1600.interrupt_mask().unwrap(),
+		PinSet::from_bits(0x0101)
+	);
+	stmpe1600.set_interrupt_mask(PinSet::empty()).unwrap();
+	assert!(stmpe1600.interrupt_mask().unwrap().is_empty());
+	i2c.done();
+}
+
+#[test]
+fn poll_changes() {
+	let mut expectations = init();
+	expectations.extend([
+		// The first sample only records the levels.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+		// Pin 0 fell and pin 8 rose, but pin 0 only reports rising edges. Pin 1 is not watched.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x02, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(0)
+		.pin_interrupt(8)
+		.build()
+		.unwrap();
+	stmpe1600
+		.pin_interrupt(0)
+		.unwrap()
+		.set_trigger(Trigger::Rising)
+		.unwrap();
+
+	let mut detector = ChangeDetector::new(PinSet::from_bits(0x0101));
+	assert_eq!(stmpe1600.poll_changes(&mut detector).unwrap().len(), 0);
+	let events = stmpe1600.poll_changes(&mut detector).unwrap();
+	// Only the level of the reported pin is included, not those of unwatched pin 1.
+	assert_eq!(events.levels(), PinSet::from_bits(0x0100));
+	assert_eq!(
+		events.collect::<Vec<_>>(),
+		[InterruptEvent {
+			pin: 8,
+			level: PinState::High,
+			edge: Edge::Rising,
+		}]
+	);
+	i2c.done();
+}
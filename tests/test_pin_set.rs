@@ -0,0 +1,34 @@
+use stmpe1600::PinSet;
+
+#[test]
+fn set_operations() {
+	let a = PinSet::from_bits(0b0011);
+	let b = PinSet::from_bits(0b0110);
+	assert_eq!(a | b, PinSet::from_bits(0b0111));
+	assert_eq!(a & b, PinSet::from_bits(0b0010));
+	assert_eq!(a ^ b, PinSet::from_bits(0b0101));
+	assert_eq!(a - b, PinSet::from_bits(0b0001));
+	assert_eq!(!PinSet::empty(), PinSet::all());
+
+	let mut set = a;
+	set |= b;
+	set -= PinSet::from_bits(0b0100);
+	assert_eq!(set, a);
+}
+
+#[test]
+fn membership_and_iteration() {
+	let mut set: PinSet = [15, 0, 7].iter().copied().collect();
+	assert_eq!(set.len(), 3);
+	assert!(set.contains(7));
+	assert!(!set.contains(8));
+	assert_eq!(set.iter().collect::<Vec<_>>(), [0, 7, 15]);
+
+	set.remove(7);
+	// Pins above 15 are never members of a set.
+	set.insert(16);
+	assert!(!set.contains(16));
+	assert_eq!(set.bits(), 0x8001);
+	assert_eq!(format!("{:?}", set), "{0, 15}");
+	assert!(PinSet::empty().is_empty());
+}
@@ -1,71 +1,443 @@
-use embedded_hal::digital::v2::{InputPin, OutputPin};
-use embedded_hal_mock::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
-use stmpe1600::{Polarity, Stmpe1600Builder, DEFAULT_ADDRESS};
+use embedded_hal::digital::{InputPin, OutputPin, PinState, StatefulOutputPin};
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, Operation, SevenBitAddress};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use std::cell::Cell;
+use std::rc::Rc;
+use stmpe1600::{
+	modes, Error, Pin, PinSet, Polarity, Stmpe1600, Stmpe1600Builder, DEFAULT_ADDRESS,
+};
 
 #[test]
 fn read_pin() {
-	let i2c = I2cMock::new(&[
+	let mut i2c = I2cMock::new(&[
 		// Check device ID.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x00]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x16]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
 		// Software reset.
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
 		// Get pin 0 state.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x10]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x01, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
 	]);
 
-	let mut stmpe1600 = Stmpe1600Builder::new(i2c).build().unwrap();
-	let input_pin = stmpe1600.pin_input(0).unwrap();
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let mut input_pin = stmpe1600.pin_input(0).unwrap();
 	assert!(input_pin.is_high().unwrap(), "Input pin in is LOW");
+	i2c.done();
 }
 
 #[test]
 fn write_pin() {
-	let i2c = I2cMock::new(&[
+	let mut i2c = I2cMock::new(&[
 		// Check device ID.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x00]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x16]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
 		// Software reset.
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
 		// Set pin 0 as an output pin.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x00]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
 		// Set pin 0 as HIGH.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x00]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
 	]);
 
-	let mut stmpe1600 = Stmpe1600Builder::new(i2c).build().unwrap();
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
 	let mut output_pin = stmpe1600.pin_output(0).unwrap();
 	output_pin.set_high().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn write_pin_uncached() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 0 as an output pin.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x14], vec![0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		// Set pin 0 as HIGH.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x12], vec![0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.register_cache(false)
+		.build()
+		.unwrap();
+	let mut output_pin = stmpe1600.pin_output(0).unwrap();
+	output_pin.set_high().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn stateful_output_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x02, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x02, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Toggle pin 1 LOW, then HIGH again.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x02, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_output(1, PinState::High)
+		.build()
+		.unwrap();
+	let mut output_pin = stmpe1600.pin_output(1).unwrap();
+	// The output state is served from the register cache.
+	assert!(output_pin.is_set_high().unwrap());
+	output_pin.toggle().unwrap();
+	assert!(output_pin.is_set_low().unwrap());
+	output_pin.toggle().unwrap();
+	assert!(output_pin.is_set_high().unwrap());
+	i2c.done();
+}
+
+#[test]
+fn stateful_output_pin_uncached() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x01, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Read back pin 0's output state from GPSR, not GPMR.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x12], vec![0x00, 0x00]),
+		// Toggle pin 0.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x12], vec![0x80, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x81, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_output(0, PinState::Low)
+		.register_cache(false)
+		.build()
+		.unwrap();
+	let mut output_pin = stmpe1600.pin_output(0).unwrap();
+	assert!(output_pin.is_set_low().unwrap());
+	output_pin.toggle().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn interrupt_to_output() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 4 as an interrupt pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x10, 0x00]),
+		// Disable its interrupt before making it an output.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x10, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let interrupt_pin = stmpe1600.pin_interrupt(4).unwrap();
+	interrupt_pin.into_output_pin().unwrap();
+	i2c.done();
 }
 
 #[test]
 fn polarity_inversion() {
-	let i2c = I2cMock::new(&[
+	let mut i2c = I2cMock::new(&[
 		// Check device ID.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x00]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x16]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
 		// Software reset.
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 0 polarity inversion to HIGH. Reads are served from the cache.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16, 0x01, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let mut pin = stmpe1600.pin_input(0).unwrap();
+	assert_eq!(pin.polarity_inversion().unwrap(), Polarity::Low);
+	pin.set_polarity_inversion(Polarity::High).unwrap();
+	assert_eq!(pin.polarity_inversion().unwrap(), Polarity::High);
+	i2c.done();
+}
+
+#[test]
+fn polarity_inversion_uncached() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
 		// Get pin 0 polarity inversion.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x16], vec![0x00, 0x00]),
 		// Set pin 0 polarity inversion to HIGH.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x00, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x16], vec![0x00, 0x00]),
 		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16, 0x01, 0x00]),
 		// Get pin 0 polarity inversion.
-		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16]),
-		I2cTransaction::read(DEFAULT_ADDRESS, vec![0x01, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x16], vec![0x01, 0x00]),
 	]);
 
-	let mut stmpe1600 = Stmpe1600Builder::new(i2c).build().unwrap();
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.register_cache(false)
+		.build()
+		.unwrap();
 	let mut pin = stmpe1600.pin_input(0).unwrap();
 	assert_eq!(pin.polarity_inversion().unwrap(), Polarity::Low);
 	pin.set_polarity_inversion(Polarity::High).unwrap();
 	assert_eq!(pin.polarity_inversion().unwrap(), Polarity::High);
+	i2c.done();
+}
+
+#[test]
+fn split_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes, with pin 3's output level set while it is still an input.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x08, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Splitting writes nothing. Set pin 3 as an output pin, which drives the level already set.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x08, 0x00]),
+		// Set pin 1 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x0A, 0x00]),
+		// Get pin 0 state.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+		// Set pin 1 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x0A, 0x00]),
+		// Read the whole port through the driver, while the pins are still in use.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x03, 0x00]),
+	]);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.output_level(3, PinState::High)
+		.build()
+		.unwrap();
+	let pins = stmpe1600.split().unwrap();
+	let mut input_pin = pins.p0;
+	let mut p3 = pins.p3.into_output_pin().unwrap();
+	assert!(p3.is_set_high().unwrap());
+	let mut output_pin = pins.p1.into_output_pin().unwrap();
+	if input_pin.is_high().unwrap() {
+		output_pin.set_high().unwrap();
+	}
+	assert_eq!(stmpe1600.read_port().unwrap(), PinSet::from_bits(0x0003));
+	assert!(matches!(stmpe1600.split(), Err(Error::AlreadySplit)));
+	assert!(output_pin.is_set_high().unwrap());
+	i2c.done();
+}
+
+#[test]
+fn split_pin_conversion_failed() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Setting pin 2 as an output pin fails, and is retried with the pin handed back.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x04, 0x00]).with_error(ErrorKind::Other),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x04, 0x00]),
+		// Set pin 2 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x04, 0x00]),
+	]);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let pins = stmpe1600.split().unwrap();
+	let (error, p2) = match pins.p2.try_into_output_pin() {
+		Ok(_) => panic!("Mode change did not fail"),
+		Err(failed) => failed,
+	};
+	assert!(matches!(error, Error::I2CError(ErrorKind::Other)));
+	let mut p2 = match p2.try_into_output_pin() {
+		Ok(pin) => pin,
+		Err((error, _)) => panic!("Mode change failed again: {:?}", error),
+	};
+	p2.set_high().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn split_configured_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes, with pin 3 as an output driven high.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x08, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x08, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Splitting fails without touching the configuration.
+	]);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_output(3, PinState::High)
+		.build()
+		.unwrap();
+	assert!(matches!(stmpe1600.split(), Err(Error::PinNotInput)));
+	i2c.done();
+}
+
+#[test]
+fn split_interrupt_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes, with pin 9 as an interrupt pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x02]),
+		// Pin 9 is put back into input mode.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.pin_interrupt(9)
+		.build()
+		.unwrap();
+	assert!(matches!(stmpe1600.split(), Err(Error::PinNotInput)));
+	// The failed split does not use up the pins.
+	stmpe1600.pin_input(9).unwrap();
+	assert!(
+		stmpe1600.split().is_ok(),
+		"Split failed after restoring pin 9"
+	);
+	i2c.done();
+}
+
+#[test]
+fn const_pins() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Set pin 15 as an output pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x80]),
+		// Set pin 15 as HIGH.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x00, 0x80]),
+		// Set pin 15 as LOW through the erased pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x00, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let mut output_pin = stmpe1600.pin_output_const::<15>().unwrap();
+	output_pin.set_high().unwrap();
+	let mut erased: Pin<'_, _, modes::Output> = output_pin.erase();
+	assert_eq!(erased.index(), 15);
+	erased.set_low().unwrap();
+	i2c.done();
+}
+
+#[test]
+fn invalid_pin() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	]);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	assert!(matches!(stmpe1600.pin_input(16), Err(Error::InvalidPin)));
+	assert!(matches!(stmpe1600.pin_output(200), Err(Error::InvalidPin)));
+	assert!(matches!(
+		stmpe1600.pin_interrupt(16),
+		Err(Error::InvalidPin)
+	));
+	i2c.done();
+}
+
+/// An I²C bus which calls back into the driver during its next transaction, while the driver is
+/// using it, as a bus shared with an interrupt handler might.
+struct ReentrantI2c {
+	i2c: I2cMock,
+	driver: Rc<Cell<Option<&'static Stmpe1600<ReentrantI2c>>>>,
+	busy: Rc<Cell<bool>>,
+}
+
+impl ErrorType for ReentrantI2c {
+	type Error = ErrorKind;
+}
+
+impl I2c for ReentrantI2c {
+	fn transaction(
+		&mut self,
+		address: SevenBitAddress,
+		operations: &mut [Operation<'_>],
+	) -> Result<(), ErrorKind> {
+		if let Some(driver) = self.driver.take() {
+			self.busy
+				.set(matches!(driver.read_port(), Err(Error::Busy)));
+		}
+		match operations {
+			[Operation::Write(bytes)] => self.i2c.write(address, bytes),
+			[Operation::Write(bytes), Operation::Read(buffer)] => {
+				self.i2c.write_read(address, bytes, buffer)
+			}
+			_ => Err(ErrorKind::Other),
+		}
+	}
+}
+
+#[test]
+fn reentrant_access() {
+	let mut i2c = I2cMock::new(&[
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		// Get the pin states, during which the bus tries to get them again.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x01, 0x00]),
+	]);
+
+	let driver = Rc::new(Cell::new(None));
+	let busy = Rc::new(Cell::new(false));
+	let bus = ReentrantI2c {
+		i2c: i2c.clone(),
+		driver: driver.clone(),
+		busy: busy.clone(),
+	};
+	let stmpe1600: &'static Stmpe1600<ReentrantI2c> =
+		Box::leak(Box::new(Stmpe1600Builder::new(bus).build().unwrap()));
+	driver.set(Some(stmpe1600));
+	assert_eq!(stmpe1600.read_port().unwrap(), PinSet::from_bits(0x0001));
+	assert!(busy.get(), "re-entrant access was not reported as busy");
+	i2c.done();
 }
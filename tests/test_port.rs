@@ -0,0 +1,147 @@
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use stmpe1600::{PinSet, Stmpe1600Builder, Trigger, DEFAULT_ADDRESS};
+
+fn init() -> Vec<I2cTransaction> {
+	vec![
+		// Check device ID.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x00], vec![0x00, 0x16]),
+		// Software reset.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x03, 0x80]),
+		// Configure pin modes.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+	]
+}
+
+#[test]
+fn read_write_port() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read GPMR.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x34, 0x12]),
+		// Write GPSR.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0xCD, 0xAB]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	assert_eq!(stmpe1600.read_port().unwrap(), PinSet::from_bits(0x1234));
+	stmpe1600.write_port(PinSet::from_bits(0xABCD)).unwrap();
+	i2c.done();
+}
+
+#[test]
+fn direction_and_polarity_masks() {
+	let mut expectations = init();
+	expectations.extend([
+		// Write GPDR.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x0F, 0x00]),
+		// Write GPPIR.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x16, 0x00, 0x80]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	assert_eq!(stmpe1600.direction().unwrap(), PinSet::empty());
+	stmpe1600.set_direction(PinSet::from_bits(0x000F)).unwrap();
+	assert_eq!(stmpe1600.direction().unwrap(), PinSet::from_bits(0x000F));
+	stmpe1600
+		.set_polarity_inversion(PinSet::from_bits(0x8000))
+		.unwrap();
+	assert_eq!(
+		stmpe1600.polarity_inversion().unwrap(),
+		PinSet::from_bits(0x8000)
+	);
+	i2c.done();
+}
+
+#[test]
+fn direction_disables_interrupts() {
+	let mut expectations = init();
+	expectations.extend([
+		// Set pin 2 as an interrupt pin.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x04, 0x00]),
+		// Pin 2 has a rising edge pending.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x04, 0x00]),
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x10], vec![0x04, 0x00]),
+		// Disable the interrupt on pin 2, then make pins 0-3 outputs.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x08, 0x00, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x0F, 0x00]),
+		// Nothing else is pending.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x0A], vec![0x00, 0x00]),
+		// Pin 2 is now tracked as an output, so making it an input only touches GPDR.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x14, 0x0B, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let mut stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	let mut pin = stmpe1600.pin_interrupt(2).unwrap();
+	pin.set_trigger(Trigger::Rising).unwrap();
+	assert!(pin.is_pending().unwrap());
+	stmpe1600.set_direction(PinSet::from_bits(0x000F)).unwrap();
+	// The interrupt latched before pin 2 became an output is dropped.
+	assert_eq!(stmpe1600.get_interrupts().unwrap(), PinSet::empty());
+	stmpe1600.pin_input(2).unwrap();
+	i2c.done();
+}
+
+#[test]
+fn uncached_direction() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read GPDR.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x14], vec![0x01, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.register_cache(false)
+		.build()
+		.unwrap();
+	assert_eq!(stmpe1600.direction().unwrap(), PinSet::from_bits(0x0001));
+	i2c.done();
+}
+
+#[test]
+fn set_and_toggle_outputs() {
+	let mut expectations = init();
+	expectations.extend([
+		// Drive pins 0 and 1 high.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x03, 0x00]),
+		// Drive pin 8 high and pin 0 low.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x02, 0x01]),
+		// Toggle pins 1 and 2.
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x04, 0x01]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone()).build().unwrap();
+	stmpe1600
+		.set_outputs(PinSet::from_bits(0x0003), PinSet::empty())
+		.unwrap();
+	stmpe1600
+		.set_outputs(PinSet::from_bits(0x0100), PinSet::from_bits(0x0001))
+		.unwrap();
+	stmpe1600.toggle_outputs(PinSet::from_bits(0x0006)).unwrap();
+	i2c.done();
+}
+
+#[test]
+fn uncached_set_outputs() {
+	let mut expectations = init();
+	expectations.extend([
+		// Read GPSR, then drive pin 0 high and pin 4 low.
+		I2cTransaction::write_read(DEFAULT_ADDRESS, vec![0x12], vec![0x10, 0x00]),
+		I2cTransaction::write(DEFAULT_ADDRESS, vec![0x12, 0x01, 0x00]),
+	]);
+	let mut i2c = I2cMock::new(&expectations);
+
+	let stmpe1600 = Stmpe1600Builder::new(i2c.clone())
+		.register_cache(false)
+		.build()
+		.unwrap();
+	stmpe1600
+		.set_outputs(PinSet::from_bits(0x0001), PinSet::from_bits(0x0010))
+		.unwrap();
+	i2c.done();
+}